@@ -58,4 +58,163 @@ pub enum ErrorCode {
   CannotPruneActiveMarket,
   #[msg("Numberical overflow")]
   NumberOverflow,
+  #[msg("Cannot divide by zero")]
+  DivideByZero,
+  #[msg("Underlying source mint must match underlying asset mint address")]
+  UnderlyingSrcMintDoesNotMatchUnderlyingAsset,
+  #[msg("Not enough underlying assets in the source account to cover the write and mint fee")]
+  NotEnoughUnderlyingAssetsInSrc,
+  #[msg("Exercise did not move the pools by the contract amounts")]
+  InvalidExerciseDelta,
+  #[msg("Fee split basis points must sum to 10,000")]
+  InvalidFeeSplit,
+  #[msg("Quote source mint must match quote asset mint address")]
+  QuoteSrcMintDoesNotMatchQuoteAsset,
+  #[msg("Not enough quote assets in the source account to cover the exercise and exercise fee")]
+  NotEnoughQuoteAssetsInSrc,
+  #[msg("Cannot burn more Writer Tokens than the Writer Token supply")]
+  BurnExceedsWriterSupply,
+  #[msg("Expiration must not be a negative timestamp")]
+  InvalidExpiration,
+  #[msg("Underlying pool mint must match underlying asset mint address")]
+  UnderlyingPoolMintDoesNotMatchUnderlyingAsset,
+  #[msg("Quote pool mint must match quote asset mint address")]
+  QuotePoolMintDoesNotMatchQuoteAsset,
+  #[msg("Provided timestamp deviates too far from the cluster clock")]
+  ClockSkew,
+  #[msg("Expiration is further out than the maximum tenor")]
+  TenorTooLong,
+  #[msg("Early close must burn equal amounts of Option Tokens and Writer Tokens")]
+  UnequalEarlyCloseBurns,
+  #[msg("OptionMarket is expired, use close_post_expiration instead")]
+  OptionMarketExpiredCantClosePosition,
+  #[msg("Underlying and quote asset pools must be different accounts")]
+  IdenticalPools,
+  #[msg("Underlying asset mint is not in the registry")]
+  UnderlyingAssetNotAllowed,
+  #[msg("Quote asset mint is not in the registry")]
+  QuoteAssetNotAllowed,
+  #[msg("Expiration is sooner than the minimum tenor")]
+  TenorTooShort,
+  #[msg("The same account was passed where distinct accounts are required")]
+  DuplicateAccount,
+  #[msg("Market expires after the deadline")]
+  ExpiryAfterDeadline,
+  #[msg("Fee accounts cannot be one of the market's pools")]
+  FeeAccountIsPool,
+  #[msg("Option Token mint has more decimals than supported")]
+  UnsupportedDecimals,
+  #[msg("Strike is outside the allowed range")]
+  StrikeOutOfRange,
+  #[msg("Exercise amount must be a whole number of contracts")]
+  UnalignedExercise,
+}
+
+impl ErrorCode {
+  /// Every variant in declaration order, for generating client side error types.
+  pub const ALL: &'static [ErrorCode] = &[
+    ErrorCode::ExpirationIsInThePast,
+    ErrorCode::QuoteAndUnderlyingAssetMustDiffer,
+    ErrorCode::QuoteOrUnderlyingAmountCannotBe0,
+    ErrorCode::OptionMarketMustBeMintAuthority,
+    ErrorCode::OptionMarketMustOwnUnderlyingAssetPool,
+    ErrorCode::OptionMarketMustOwnQuoteAssetPool,
+    ErrorCode::ExpectedSPLTokenProgramId,
+    ErrorCode::MintFeeMustBeOwnedByFeeOwner,
+    ErrorCode::ExerciseFeeMustBeOwnedByFeeOwner,
+    ErrorCode::MintFeeTokenMustMatchUnderlyingAsset,
+    ErrorCode::ExerciseFeeTokenMustMatchQuoteAsset,
+    ErrorCode::OptionMarketExpiredCantMint,
+    ErrorCode::UnderlyingPoolAccountDoesNotMatchMarket,
+    ErrorCode::OptionTokenMintDoesNotMatchMarket,
+    ErrorCode::WriterTokenMintDoesNotMatchMarket,
+    ErrorCode::MintFeeKeyDoesNotMatchOptionMarket,
+    ErrorCode::SizeCantBeLessThanEqZero,
+    ErrorCode::ExerciseFeeKeyDoesNotMatchOptionMarket,
+    ErrorCode::QuotePoolAccountDoesNotMatchMarket,
+    ErrorCode::UnderlyingDestMintDoesNotMatchUnderlyingAsset,
+    ErrorCode::FeeOwnerDoesNotMatchProgram,
+    ErrorCode::OptionMarketExpiredCantExercise,
+    ErrorCode::OptionMarketNotExpiredCantClose,
+    ErrorCode::NotEnoughQuoteAssetsInPool,
+    ErrorCode::InvalidAuth,
+    ErrorCode::CoinMintIsNotOptionMint,
+    ErrorCode::CannotPruneActiveMarket,
+    ErrorCode::NumberOverflow,
+    ErrorCode::DivideByZero,
+    ErrorCode::UnderlyingSrcMintDoesNotMatchUnderlyingAsset,
+    ErrorCode::NotEnoughUnderlyingAssetsInSrc,
+    ErrorCode::InvalidExerciseDelta,
+    ErrorCode::InvalidFeeSplit,
+    ErrorCode::QuoteSrcMintDoesNotMatchQuoteAsset,
+    ErrorCode::NotEnoughQuoteAssetsInSrc,
+    ErrorCode::BurnExceedsWriterSupply,
+    ErrorCode::InvalidExpiration,
+    ErrorCode::UnderlyingPoolMintDoesNotMatchUnderlyingAsset,
+    ErrorCode::QuotePoolMintDoesNotMatchQuoteAsset,
+    ErrorCode::ClockSkew,
+    ErrorCode::TenorTooLong,
+    ErrorCode::UnequalEarlyCloseBurns,
+    ErrorCode::OptionMarketExpiredCantClosePosition,
+    ErrorCode::IdenticalPools,
+    ErrorCode::UnderlyingAssetNotAllowed,
+    ErrorCode::QuoteAssetNotAllowed,
+    ErrorCode::TenorTooShort,
+    ErrorCode::DuplicateAccount,
+    ErrorCode::ExpiryAfterDeadline,
+    ErrorCode::FeeAccountIsPool,
+    ErrorCode::UnsupportedDecimals,
+    ErrorCode::StrikeOutOfRange,
+    ErrorCode::UnalignedExercise,
+  ];
+
+  /// The custom program error code this variant is returned as.
+  pub fn code(self) -> u32 {
+    self as u32 + ERROR_CODE_OFFSET
+  }
+
+  /// `(code, name)` for every variant, where `name` is the variant identifier.
+  pub fn table() -> Vec<(u32, String)> {
+    Self::ALL.iter().map(|e| (e.code(), format!("{:?}", e))).collect()
+  }
+}
+
+/// The code of the first variant. Anchor's `#[error]` numbers custom errors from here.
+const ERROR_CODE_OFFSET: u32 = 300;
+
+// `ALL` must cover every variant; point this at the new last variant when appending.
+const _: [(); 0] = [(); (ErrorCode::ALL.len() != ErrorCode::UnalignedExercise as usize + 1) as usize];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  #[test]
+  fn table_covers_every_variant() {
+    let table = ErrorCode::table();
+    assert_eq!(table.len(), ErrorCode::ALL.len());
+    assert_eq!(table.len(), ErrorCode::UnalignedExercise as usize + 1);
+    assert_eq!(table[0], (ERROR_CODE_OFFSET, "ExpirationIsInThePast".to_string()));
+    assert_eq!(table[table.len() - 1].1, "UnalignedExercise");
+  }
+
+  #[test]
+  fn offset_matches_anchor() {
+    let first: ProgramError = ErrorCode::ExpirationIsInThePast.into();
+    assert_eq!(first, ProgramError::Custom(ERROR_CODE_OFFSET));
+  }
+
+  #[test]
+  fn table_codes_and_names_are_unique() {
+    let table = ErrorCode::table();
+    let codes: HashSet<u32> = table.iter().map(|(code, _)| *code).collect();
+    let names: HashSet<&String> = table.iter().map(|(_, name)| name).collect();
+    assert_eq!(codes.len(), table.len());
+    assert_eq!(names.len(), table.len());
+    for (index, error) in ErrorCode::ALL.iter().enumerate() {
+      assert_eq!(error.code(), ERROR_CODE_OFFSET + index as u32);
+      assert_eq!(ProgramError::from(*error), ProgramError::Custom(error.code()));
+    }
+  }
 }
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::OptionMarket;
+
+/// Emitted when a writer settles Writer Tokens against the pools, either by
+/// reclaiming underlying after expiration with `close_post_expiration` or by
+/// burning them for exercised quote with `burn_writer_for_quote`.
+///
+/// Logged as base64 `Program data:` in Anchor's event format: the 8 byte event
+/// discriminator followed by the Borsh serialized fields in declaration order.
+#[event]
+pub struct SettlementEvent {
+    /// The market's Option Token mint, which uniquely identifies the market
+    pub option_mint: Pubkey,
+    /// `OptionMarket::fingerprint`, for correlating logs
+    pub fingerprint: u64,
+    /// Underlying transferred from the pool to the writer, in base units
+    pub underlying_reclaimed: u64,
+    /// Quote transferred from the pool to the writer, in base units
+    pub quote_distributed: u64,
+}
+
+/// Emit a `SettlementEvent` for `market`
+pub fn emit_settlement(market: &OptionMarket, underlying_reclaimed: u64, quote_distributed: u64) {
+    emit!(SettlementEvent {
+        option_mint: market.option_mint,
+        fingerprint: market.fingerprint(),
+        underlying_reclaimed,
+        quote_distributed,
+    });
+}
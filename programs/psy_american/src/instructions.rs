@@ -0,0 +1,151 @@
+use anchor_lang::{prelude::*, InstructionData};
+
+use crate::{errors, instruction};
+
+/// Instruction data for `exercise_option_v2` exercising `contracts` contracts
+pub fn exercise_data(contracts: u64) -> Vec<u8> {
+    instruction::ExerciseOptionV2 { size: contracts }.data()
+}
+
+/// Instruction data for `exercise_option_v2` exercising exactly one contract
+pub fn exercise_one_data() -> Vec<u8> {
+    exercise_data(1)
+}
+
+/// The indices of the accounts that must be passed as writable to the instruction
+/// named `instruction_name`, or `None` for an unknown instruction. These follow the
+/// order of each instruction's Accounts struct and must be kept in sync with it.
+///
+/// NOTE: the fee accounts passed as remaining accounts to `mint_option` and
+/// `exercise_option` are writable as well, and are not included.
+pub fn writable_accounts(instruction_name: &str) -> Option<&'static [usize]> {
+    let indices: &'static [usize] = match instruction_name {
+        "initialize_market" => &[0, 3, 4, 5, 6, 7],
+        "mint_option" => &[0, 2, 3, 4, 5, 6, 7, 9],
+        "mint_option_v2" => &[0, 2, 3, 4, 5, 6, 7],
+        "exercise_option" => &[0, 1, 3, 4, 5, 6, 7, 8, 9],
+        "exercise_option_v2" => &[0, 1, 3, 4, 5, 6, 7, 8],
+        "close_post_expiration" => &[2, 3, 4, 5],
+        "close_option_position" => &[2, 3, 4, 5, 6, 7],
+        "burn_writer_for_quote" => &[2, 3, 4, 5],
+        "init_serum_market" => &[0, 1, 2, 9, 10, 11, 12, 13, 14],
+        _ => return None,
+    };
+    Some(indices)
+}
+
+/// Validate at least `expected` accounts were passed before indexing into them
+pub fn require_account_count(accounts: &[AccountInfo], expected: usize) -> Result<(), ProgramError> {
+    if accounts.len() < expected {
+        return Err(ProgramError::NotEnoughAccountKeys)
+    }
+    Ok(())
+}
+
+/// Validate no two of `keys` are the same account
+pub fn require_distinct(keys: &[&Pubkey]) -> Result<(), ProgramError> {
+    for (i, key) in keys.iter().enumerate() {
+        if keys[i + 1..].contains(key) {
+            return Err(errors::ErrorCode::DuplicateAccount.into())
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exercise_one_data_decodes_to_one_contract() {
+        let data = exercise_one_data();
+        assert_eq!(data, exercise_data(1));
+        let decoded = instruction::ExerciseOptionV2::try_from_slice(&data[8..]).unwrap();
+        assert_eq!(decoded.size, 1);
+    }
+
+    #[test]
+    fn exercise_data_starts_with_the_sighash() {
+        let data = exercise_data(u64::MAX);
+        let sighash = solana_program::hash::hash(b"global:exercise_option_v2");
+        assert_eq!(data[..8], sighash.to_bytes()[..8]);
+        assert_eq!(data[..8], exercise_one_data()[..8]);
+        assert_eq!(instruction::ExerciseOptionV2::try_from_slice(&data[8..]).unwrap().size, u64::MAX);
+    }
+
+    fn writable_indices(metas: &[AccountMeta]) -> Vec<usize> {
+        metas.iter().enumerate().filter(|(_, meta)| meta.is_writable).map(|(index, _)| index).collect()
+    }
+
+    #[test]
+    fn writable_accounts_match_mint_option() {
+        let metas = crate::accounts::MintOption {
+            user_authority: Pubkey::new_unique(),
+            underlying_asset_mint: Pubkey::new_unique(),
+            underlying_asset_pool: Pubkey::new_unique(),
+            underlying_asset_src: Pubkey::new_unique(),
+            option_mint: Pubkey::new_unique(),
+            minted_option_dest: Pubkey::new_unique(),
+            writer_token_mint: Pubkey::new_unique(),
+            minted_writer_token_dest: Pubkey::new_unique(),
+            option_market: Pubkey::new_unique(),
+            fee_owner: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            associated_token_program: Pubkey::new_unique(),
+            clock: Pubkey::new_unique(),
+            rent: Pubkey::new_unique(),
+            system_program: Pubkey::new_unique(),
+        }.to_account_metas(None);
+        assert_eq!(writable_accounts("mint_option").unwrap(), &writable_indices(&metas)[..]);
+    }
+
+    #[test]
+    fn writable_accounts_match_exercise_option() {
+        let metas = crate::accounts::ExerciseOption {
+            user_authority: Pubkey::new_unique(),
+            option_authority: Pubkey::new_unique(),
+            option_market: Pubkey::new_unique(),
+            option_mint: Pubkey::new_unique(),
+            exerciser_option_token_src: Pubkey::new_unique(),
+            underlying_asset_pool: Pubkey::new_unique(),
+            underlying_asset_dest: Pubkey::new_unique(),
+            quote_asset_pool: Pubkey::new_unique(),
+            quote_asset_src: Pubkey::new_unique(),
+            fee_owner: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            system_program: Pubkey::new_unique(),
+            clock: Pubkey::new_unique(),
+        }.to_account_metas(None);
+        assert_eq!(writable_accounts("exercise_option").unwrap(), &writable_indices(&metas)[..]);
+    }
+
+    #[test]
+    fn writable_accounts_unknown_instruction() {
+        assert_eq!(writable_accounts("close_market"), None);
+    }
+
+    #[test]
+    fn require_account_count_rejects_too_few() {
+        let key = Pubkey::new_unique();
+        let mut lamports = [0u64; 2];
+        let [first, second] = &mut lamports;
+        let (mut first_data, mut second_data) = ([0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&key, false, false, first, &mut first_data, &key, false, 0),
+            AccountInfo::new(&key, false, false, second, &mut second_data, &key, false, 0),
+        ];
+        assert_eq!(require_account_count(&accounts, 2), Ok(()));
+        assert_eq!(require_account_count(&accounts, 1), Ok(()));
+        assert_eq!(require_account_count(&accounts, 3), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(require_account_count(&[], 1), Err(ProgramError::NotEnoughAccountKeys));
+    }
+
+    #[test]
+    fn require_distinct_rejects_duplicates() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(require_distinct(&[&a, &b, &c]), Ok(()));
+        assert_eq!(require_distinct(&[]), Ok(()));
+        assert_eq!(require_distinct(&[&a, &b, &a]), Err(errors::ErrorCode::DuplicateAccount.into()));
+        assert_eq!(require_distinct(&[&a, &c, &c]), Err(errors::ErrorCode::DuplicateAccount.into()));
+    }
+}
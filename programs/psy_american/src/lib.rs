@@ -1,5 +1,8 @@
 pub mod errors;
+pub mod events;
 pub mod fees;
+pub mod instructions;
+pub mod market;
 pub mod serum_proxy;
 
 use anchor_lang::{AccountsExit, Key, prelude::*};
@@ -58,6 +61,7 @@ pub mod psy_american {
         option_market.exercise_fee_account = fee_accounts.exercise_fee_key;
         option_market.expired = false;
         option_market.bump_seed = bump_seed;
+        option_market.validate_parameters()?;
 
         Ok(())
     }
@@ -111,14 +115,8 @@ pub mod psy_american {
         let underlying_transfer_amount = option_market.underlying_amount_per_contract.checked_mul(size).unwrap();
         token::transfer(cpi_ctx, underlying_transfer_amount)?;
 
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
 
         // Mint a new OptionToken(s)
@@ -159,14 +157,8 @@ pub mod psy_american {
         let underlying_transfer_amount = option_market.underlying_amount_per_contract.checked_mul(size).unwrap();
         token::transfer(cpi_ctx, underlying_transfer_amount)?;
 
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
 
         // Mint a new OptionToken(s)
@@ -195,14 +187,8 @@ pub mod psy_american {
     #[access_control(ExerciseOption::accounts(&ctx) ExerciseOption::unexpired_market(&ctx))]
     pub fn exercise_option<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ExerciseOption<'info>>, size: u64) -> ProgramResult {
         let option_market = &ctx.accounts.option_market;
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
         // Burn the size of option tokens
         let cpi_ctx = CpiContext::new_with_signer(
@@ -274,14 +260,8 @@ pub mod psy_american {
     #[access_control(ExerciseOptionV2::accounts(&ctx) ExerciseOptionV2::unexpired_market(&ctx))]
     pub fn exercise_option_v2<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ExerciseOptionV2<'info>>, size: u64) -> ProgramResult {
         let option_market = &ctx.accounts.option_market;
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
         // Burn the size of option tokens
         let cpi_ctx = CpiContext::new_with_signer(
@@ -323,14 +303,8 @@ pub mod psy_american {
     #[access_control(ClosePostExp::accounts(&ctx) ClosePostExp::expired_market(&ctx))]
     pub fn close_post_expiration(ctx: Context<ClosePostExp>, size: u64) -> ProgramResult {
         let option_market = &ctx.accounts.option_market;
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
 
         // Burn the size of WriterTokens
@@ -355,20 +329,16 @@ pub mod psy_american {
         let cpi_ctx = CpiContext::new_with_signer(cpi_token_program.to_account_info(), cpi_accounts, signer);
         let underlying_transfer_amount = option_market.underlying_amount_per_contract.checked_mul(size).unwrap();
         token::transfer(cpi_ctx, underlying_transfer_amount)?;
+
+        events::emit_settlement(option_market, underlying_transfer_amount, 0);
         Ok(())
     }
 
     #[access_control(CloseOptionPosition::accounts(&ctx))]
     pub fn close_option_position(ctx: Context<CloseOptionPosition>, size: u64) -> ProgramResult {
         let option_market = &ctx.accounts.option_market;
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
 
         // Burn the size of WriterTokens
@@ -412,14 +382,8 @@ pub mod psy_american {
     #[access_control(BurnWriterForQuote::accounts(&ctx) BurnWriterForQuote::quotes_in_pool(&ctx, size))]
     pub fn burn_writer_for_quote(ctx: Context<BurnWriterForQuote>, size: u64)  -> ProgramResult {
         let option_market = &ctx.accounts.option_market;
-        let seeds = &[
-            option_market.underlying_asset_mint.as_ref(),
-            option_market.quote_asset_mint.as_ref(),
-            &option_market.underlying_amount_per_contract.to_le_bytes(),
-            &option_market.quote_amount_per_contract.to_le_bytes(),
-            &option_market.expiration_unix_timestamp.to_le_bytes(),
-            &[option_market.bump_seed]
-        ];
+        let seed_buf = option_market.signer_seeds();
+        let seeds = seed_buf.seeds();
         let signer = &[&seeds[..]];
 
         // Burn the size of WriterTokens
@@ -444,8 +408,8 @@ pub mod psy_american {
         let cpi_ctx = CpiContext::new_with_signer(cpi_token_program.to_account_info(), cpi_accounts, signer);
         let quote_transfer_amount = option_market.quote_amount_per_contract.checked_mul(size).unwrap();
         token::transfer(cpi_ctx, quote_transfer_amount)?;
-        
 
+        events::emit_settlement(option_market, 0, quote_transfer_amount);
         Ok(())
     }
 
@@ -604,10 +568,8 @@ fn validate_exercise_fee_acct<'c, 'info>(
         if exercise_fee_account.owner != fees::fee_owner_key::ID {
             return Err(errors::ErrorCode::ExerciseFeeMustBeOwnedByFeeOwner.into()) 
         }
-        // check that the mint fee recipient account's mint is also the underlying mint
-        if exercise_fee_account.mint != option_market.quote_asset_mint {
-            return Err(errors::ErrorCode::ExerciseFeeTokenMustMatchQuoteAsset.into())
-        }
+        // check that the exercise fee recipient account's mint is also the quote mint
+        option_market.validate_exercise_fee_account(&exercise_fee_account.mint)?;
         // Check the exercise fee account matches the one on the OptionMarket
         if *exercise_fee_recipient.key != option_market.exercise_fee_account {
             return Err(errors::ErrorCode::ExerciseFeeKeyDoesNotMatchOptionMarket.into())
@@ -863,9 +825,7 @@ impl<'info> ExerciseOption<'info> {
         }
 
         // Validate the option mint is the same as on the OptionMarket
-        if *ctx.accounts.option_mint.to_account_info().key != ctx.accounts.option_market.option_mint {
-            return Err(errors::ErrorCode::OptionTokenMintDoesNotMatchMarket.into())
-        }
+        ctx.accounts.option_market.validate_option_mint(ctx.accounts.option_mint.to_account_info().key)?;
 
         // Validate the underlying destination has the same mint as the pool
         if ctx.accounts.underlying_asset_dest.mint != ctx.accounts.option_market.underlying_asset_mint {
@@ -882,6 +842,14 @@ impl<'info> ExerciseOption<'info> {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // Validate the exerciser's accounts are not the pools themselves
+        instructions::require_distinct(&[
+            ctx.accounts.quote_asset_src.to_account_info().key,
+            ctx.accounts.quote_asset_pool.to_account_info().key,
+            ctx.accounts.underlying_asset_dest.to_account_info().key,
+            ctx.accounts.underlying_asset_pool.to_account_info().key,
+        ])?;
+
         Ok(())
     }
     fn unexpired_market(ctx: &Context<ExerciseOption>) -> Result<(), ProgramError> {
@@ -930,15 +898,21 @@ impl<'info> ExerciseOptionV2<'info> {
         }
 
         // Validate the option mint is the same as on the OptionMarket
-        if ctx.accounts.option_mint.key() != ctx.accounts.option_market.option_mint {
-            return Err(errors::ErrorCode::OptionTokenMintDoesNotMatchMarket.into())
-        }
+        ctx.accounts.option_market.validate_option_mint(&ctx.accounts.option_mint.key())?;
 
         // Validate the underlying destination has the same mint as the pool
         if ctx.accounts.underlying_asset_dest.mint != ctx.accounts.option_market.underlying_asset_mint {
             return Err(errors::ErrorCode::UnderlyingDestMintDoesNotMatchUnderlyingAsset.into())
         }
 
+        // Validate the exerciser's accounts are not the pools themselves
+        instructions::require_distinct(&[
+            ctx.accounts.quote_asset_src.to_account_info().key,
+            ctx.accounts.quote_asset_pool.to_account_info().key,
+            ctx.accounts.underlying_asset_dest.to_account_info().key,
+            ctx.accounts.underlying_asset_pool.to_account_info().key,
+        ])?;
+
         Ok(())
     }
     fn unexpired_market(ctx: &Context<ExerciseOptionV2>) -> Result<(), ProgramError> {
@@ -975,9 +949,7 @@ impl<'info> ClosePostExp<'info> {
         }
 
         // Validate the writer mint is the same as on the OptionMarket
-        if *ctx.accounts.writer_token_mint.to_account_info().key != ctx.accounts.option_market.writer_token_mint {
-            return Err(errors::ErrorCode::WriterTokenMintDoesNotMatchMarket.into())
-        }
+        ctx.accounts.option_market.validate_writer_mint(ctx.accounts.writer_token_mint.to_account_info().key)?;
 
         // Validate the underlying destination has the same mint as the option underlying
         if ctx.accounts.underlying_asset_dest.mint != ctx.accounts.option_market.underlying_asset_mint {
@@ -1019,9 +991,7 @@ pub struct CloseOptionPosition<'info> {
 impl<'info> CloseOptionPosition<'info> {
     fn accounts(ctx: &Context<CloseOptionPosition>) -> ProgramResult {
         // Validate the WriterToken mint is the same as the OptionMarket
-        if *ctx.accounts.writer_token_mint.to_account_info().key != ctx.accounts.option_market.writer_token_mint {
-            return Err(errors::ErrorCode::WriterTokenMintDoesNotMatchMarket.into())
-        }
+        ctx.accounts.option_market.validate_writer_mint(ctx.accounts.writer_token_mint.to_account_info().key)?;
 
         // Validate the OptionToken mint is the same as the OptionMarket
         if *ctx.accounts.option_token_mint.to_account_info().key != ctx.accounts.option_market.option_mint {
@@ -1062,9 +1032,7 @@ impl<'info> BurnWriterForQuote<'info> {
         }
 
         // Validate WriteToken mint matches the OptionMarket
-        if ctx.accounts.writer_token_mint.key() != ctx.accounts.option_market.writer_token_mint {
-            return Err(errors::ErrorCode::WriterTokenMintDoesNotMatchMarket.into())
-        }
+        ctx.accounts.option_market.validate_writer_mint(&ctx.accounts.writer_token_mint.key())?;
 
         Ok(())
     }
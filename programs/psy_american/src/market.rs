@@ -0,0 +1,2876 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_program::{clock::UnixTimestamp, program_option::COption, program_pack::Pack, system_program, sysvar};
+use spl_token::state::Account as SPLTokenAccount;
+use std::convert::TryFrom;
+
+use crate::{errors, fees, instructions, OptionMarket};
+
+/// Whether a market is read as a call or a put.
+///
+/// Every OptionMarket is a physically settled American option. A put is written
+/// by making the cash asset (e.g. USDC) the underlying and the asset being sold
+/// the quote, so which reading applies is up to the caller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketType {
+    Call,
+    Put,
+}
+
+/// The actions a user can take on an OptionMarket
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketAction {
+    Mint,
+    Exercise,
+    ClosePostExpiration,
+    ClosePosition,
+    BurnWriterForQuote,
+}
+
+/// Where a market is in its lifecycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketPhase {
+    /// Options can be written and exercised
+    Active,
+    /// Options can no longer be written or exercised, and writers can reclaim their
+    /// collateral with `close_post_expiration`
+    Expired,
+}
+
+/// How a margin system classifies a market, by tenor and moneyness.
+/// See `OptionMarket::risk_bucket` for the boundaries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RiskBucket {
+    NearInTheMoney,
+    NearAtTheMoney,
+    NearOutOfTheMoney,
+    FarInTheMoney,
+    FarAtTheMoney,
+    FarOutOfTheMoney,
+}
+
+/// The direction funds move in when an option is exercised
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExerciseFlow {
+    /// The pool that pays out to the holder
+    pub debited_pool: Pubkey,
+    /// The pool that receives the holder's payment
+    pub credited_pool: Pubkey,
+    /// The mint of the asset the holder pays with
+    pub holder_delivers_mint: Pubkey,
+    /// The mint of the asset the holder receives
+    pub holder_receives_mint: Pubkey,
+}
+
+/// How the fee accounts stored on an OptionMarket were chosen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeConvention {
+    /// Every fee account is the FEE_OWNER's associated token account for the fee's mint
+    AssociatedToken,
+    /// At least one fee account is some other token account owned by the FEE_OWNER
+    Raw,
+}
+
+/// Everything an off-chain pricing model needs about a market, in consistent units.
+///
+/// `strike` and `spot` are both the value of one contract's asset leg in base
+/// units of the cash asset, the same convention `intrinsic_value` uses.
+#[cfg(feature = "pricing")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PricingInputs {
+    pub strike: f64,
+    pub time_to_expiry_years: f64,
+    pub is_call: bool,
+    pub spot: f64,
+}
+
+/// Rough compute unit costs used by `OptionMarket::estimated_exercise_cu`
+const EXERCISE_BASE_CU: u32 = 30_000;
+const CPI_CU: u32 = 10_000;
+
+/// 64-bit FNV-1a parameters used by `OptionMarket::fingerprint`
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Boundaries used by `OptionMarket::risk_bucket`
+const NEAR_EXPIRY_SECS: i64 = 30 * 24 * 60 * 60;
+const AT_THE_MONEY_BPS: i128 = 500;
+
+const SECONDS_PER_WEEK: i64 = 7 * 24 * 60 * 60;
+#[cfg(feature = "pricing")]
+const SECONDS_PER_YEAR: f64 = 31_557_600.0;
+
+/// Owns the seeds of an OptionMarket's program address, including its bump seed,
+/// so they can be borrowed for as long as a signed CPI needs them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignerSeedBuf {
+    underlying_asset_mint: Pubkey,
+    quote_asset_mint: Pubkey,
+    underlying_amount_per_contract: [u8; 8],
+    quote_amount_per_contract: [u8; 8],
+    expiration_unix_timestamp: [u8; 8],
+    bump_seed: [u8; 1],
+}
+
+impl SignerSeedBuf {
+    /// The seeds in the order the market address is derived from, for use as
+    /// `&[&seeds[..]]` signer seeds
+    pub fn seeds(&self) -> [&[u8]; 6] {
+        [
+            self.underlying_asset_mint.as_ref(),
+            self.quote_asset_mint.as_ref(),
+            &self.underlying_amount_per_contract,
+            &self.quote_amount_per_contract,
+            &self.expiration_unix_timestamp,
+            &self.bump_seed,
+        ]
+    }
+}
+
+/// Describes an account an instruction expects
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountRole {
+    /// The field name on the instruction's Accounts struct
+    pub name: &'static str,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    /// What the account itself must be
+    pub check: AccountCheck,
+}
+
+/// What an account passed for an `AccountRole` must be, besides its signer and
+/// writable flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountCheck {
+    /// Any account
+    Any,
+    /// The FEE_OWNER
+    FeeOwner,
+    /// The SPL Token program
+    TokenProgram,
+    /// The associated token account program
+    AssociatedTokenProgram,
+    /// The system program
+    SystemProgram,
+    /// The rent sysvar
+    Rent,
+    /// The clock sysvar
+    Clock,
+    /// An account the instruction creates with `init`: still owned by the system
+    /// program and holding no data
+    Uninitialized,
+    /// An `Uninitialized` account at the program address derived from the
+    /// OptionMarket key and this seed
+    MarketAddress(&'static [u8]),
+}
+
+impl AccountCheck {
+    /// Validate `account` is what this check expects. `option_market` is the key
+    /// `MarketAddress` accounts are derived from.
+    pub fn validate(&self, account: &AccountInfo, option_market: &Pubkey, program_id: &Pubkey) -> Result<(), ProgramError> {
+        let (expected, error): (Pubkey, ProgramError) = match self {
+            AccountCheck::Any => return Ok(()),
+            AccountCheck::FeeOwner => (fees::fee_owner_key::ID, errors::ErrorCode::FeeOwnerDoesNotMatchProgram.into()),
+            AccountCheck::TokenProgram => (spl_token::ID, errors::ErrorCode::ExpectedSPLTokenProgramId.into()),
+            AccountCheck::AssociatedTokenProgram => (anchor_spl::associated_token::ID, ProgramError::IncorrectProgramId),
+            AccountCheck::SystemProgram => (system_program::ID, ProgramError::IncorrectProgramId),
+            AccountCheck::Rent => (sysvar::rent::ID, ProgramError::InvalidArgument),
+            AccountCheck::Clock => (sysvar::clock::ID, ProgramError::InvalidArgument),
+            AccountCheck::Uninitialized => {
+                if account.owner != &system_program::ID {
+                    return Err(ProgramError::IllegalOwner)
+                }
+                if !account.data_is_empty() {
+                    return Err(ProgramError::AccountAlreadyInitialized)
+                }
+                return Ok(())
+            },
+            AccountCheck::MarketAddress(seed) => {
+                AccountCheck::Uninitialized.validate(account, option_market, program_id)?;
+                let (address, _) = Pubkey::find_program_address(&[option_market.as_ref(), seed], program_id);
+                (address, ProgramError::InvalidSeeds)
+            },
+        };
+        if *account.key != expected {
+            return Err(error)
+        }
+        Ok(())
+    }
+}
+
+const fn role(name: &'static str, is_signer: bool, is_writable: bool, check: AccountCheck) -> AccountRole {
+    AccountRole { name, is_signer, is_writable, check }
+}
+
+/// The position of `option_market` in `INIT_ACCOUNT_LAYOUT`
+const INIT_OPTION_MARKET_INDEX: usize = 7;
+
+/// Must be kept in sync with the `InitializeMarket` Accounts struct
+const INIT_ACCOUNT_LAYOUT: [AccountRole; 14] = [
+    role("authority", true, true, AccountCheck::Any),
+    role("underlying_asset_mint", false, false, AccountCheck::Any),
+    role("quote_asset_mint", false, false, AccountCheck::Any),
+    role("option_mint", false, true, AccountCheck::MarketAddress(b"optionToken")),
+    role("writer_token_mint", false, true, AccountCheck::MarketAddress(b"writerToken")),
+    role("quote_asset_pool", false, true, AccountCheck::MarketAddress(b"quoteAssetPool")),
+    role("underlying_asset_pool", false, true, AccountCheck::MarketAddress(b"underlyingAssetPool")),
+    role("option_market", false, true, AccountCheck::Uninitialized),
+    role("fee_owner", false, false, AccountCheck::FeeOwner),
+    role("token_program", false, false, AccountCheck::TokenProgram),
+    role("associated_token_program", false, false, AccountCheck::AssociatedTokenProgram),
+    role("rent", false, false, AccountCheck::Rent),
+    role("system_program", false, false, AccountCheck::SystemProgram),
+    role("clock", false, false, AccountCheck::Clock),
+];
+
+/// Upper bound for `OptionMarket::LEN`.
+///
+/// Every market pays rent for its account and every instruction reads it, so the
+/// layout should only grow deliberately. 512 bytes leaves room for roughly seven
+/// more pubkeys on top of the current 282 bytes of fields.
+pub const MAX_MARKET_LEN: usize = 512;
+
+// Fails to compile when OptionMarket::LEN exceeds MAX_MARKET_LEN. This uses an array
+// length rather than `assert!` so it builds on toolchains without const panics.
+const _: [(); 0] = [(); (OptionMarket::LEN > MAX_MARKET_LEN) as usize];
+
+impl OptionMarket {
+    /// Size of the serialized OptionMarket fields. This does not include the 8 byte
+    /// account discriminator Anchor prepends to the account data. Keep this in sync
+    /// with the fields on the struct.
+    pub const LEN: usize = 32 * 4 + 8 * 3 + 32 * 4 + 1 + 1;
+
+    /// Size of the account discriminator Anchor prepends to the account data
+    pub const DISCRIMINATOR_LEN: usize = 8;
+
+    /// The full size of an OptionMarket account: the discriminator followed by
+    /// `LEN` bytes of fields. The account has no reserved padding, so this is the
+    /// space `initialize_market` allocates.
+    pub const fn account_size() -> usize {
+        Self::DISCRIMINATOR_LEN + Self::LEN
+    }
+
+    /// The lamports an OptionMarket account needs to be rent exempt
+    pub fn rent_exempt_lamports(rent: &Rent) -> u64 {
+        rent.minimum_balance(Self::account_size())
+    }
+
+    /// Returns the strike an exercise actually settled at, as the reduced ratio
+    /// `(quote_received, underlying_removed)`.
+    ///
+    /// An exercise at the contractual strike reduces to the same ratio as
+    /// `quote_amount_per_contract / underlying_amount_per_contract`.
+    pub fn realized_strike(&self, quote_received: u64, underlying_removed: u64) -> Result<(u64, u64), ProgramError> {
+        if underlying_removed == 0 {
+            return Err(errors::ErrorCode::DivideByZero.into())
+        }
+        Ok(reduce(quote_received, underlying_removed))
+    }
+
+    /// The notional size of a single contract.
+    ///
+    /// NOTE: notional is expressed in base units of the **underlying asset**, not
+    /// in the quote asset or whole tokens.
+    pub fn notional_per_contract(&self) -> u64 {
+        self.underlying_amount_per_contract
+    }
+
+    /// The notional size of `contracts` contracts, in base units of the underlying asset
+    pub fn total_notional(&self, contracts: u64) -> Result<u64, ProgramError> {
+        Ok(self.notional_per_contract().checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The mint fee, in base units of the underlying asset, charged for writing
+    /// `contracts` contracts.
+    ///
+    /// NOTE: markets whose per contract fee rounds down to 0 charge
+    /// `NFT_MINT_LAMPORTS` in SOL instead, which is not included here.
+    pub fn mint_fee_for(&self, contracts: u64) -> Result<u64, ProgramError> {
+        Ok(fees::fee_amount(self.underlying_amount_per_contract).checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The underlying a writer transfers to write `contracts` contracts: the
+    /// collateral locked in the pool plus the mint fee. The fee is rounded down per
+    /// contract before being multiplied by `contracts`, matching `mint_option`.
+    pub fn gross_underlying_for_contracts(&self, contracts: u64) -> Result<u64, ProgramError> {
+        Ok(self.total_notional(contracts)?
+            .checked_add(self.mint_fee_for(contracts)?)
+            .ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// Validate the writer's underlying source account before transferring from it.
+    /// The balance must cover both the collateral locked for `contracts` and the
+    /// mint fee.
+    pub fn validate_write_source(&self, src_mint: &Pubkey, src_balance: u64, contracts: u64) -> Result<(), ProgramError> {
+        if *src_mint != self.underlying_asset_mint {
+            return Err(errors::ErrorCode::UnderlyingSrcMintDoesNotMatchUnderlyingAsset.into())
+        }
+        if src_balance < self.gross_underlying_for_contracts(contracts)? {
+            return Err(errors::ErrorCode::NotEnoughUnderlyingAssetsInSrc.into())
+        }
+        Ok(())
+    }
+
+    /// The value of exercising one contract right now, in base units of the cash
+    /// asset.
+    ///
+    /// `spot_price` is the value of one contract's non cash leg measured in base
+    /// units of the cash leg:
+    /// - Call: the value of `underlying_amount_per_contract` in the quote asset
+    /// - Put: the value of `quote_amount_per_contract` in the underlying asset
+    pub fn intrinsic_value(&self, spot_price: u64, market_type: MarketType) -> u64 {
+        match market_type {
+            MarketType::Call => spot_price.saturating_sub(self.quote_amount_per_contract),
+            MarketType::Put => self.underlying_amount_per_contract.saturating_sub(spot_price),
+        }
+    }
+
+    /// The part of a per contract premium that is not intrinsic value. Returns 0
+    /// when the premium is below the intrinsic value.
+    ///
+    /// `spot_price` follows the same convention as `intrinsic_value`.
+    pub fn time_value(&self, premium_per_contract: u64, spot_price: u64, market_type: MarketType) -> Result<u64, ProgramError> {
+        let intrinsic = self.intrinsic_value(spot_price, market_type);
+        if premium_per_contract < intrinsic {
+            return Ok(0)
+        }
+        Ok(premium_per_contract.checked_sub(intrinsic).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// Determine whether the market's fee accounts follow the associated token
+    /// account convention.
+    ///
+    /// Only fees that are actually charged in tokens are considered. When a fee
+    /// rounds down to 0 the market stores the FEE_OWNER key itself, which is not
+    /// a token account. A market that charges no token fees at all is reported
+    /// as `AssociatedToken` since there is nothing that deviates from it.
+    ///
+    /// NOTE: this derives program addresses, so it is best kept off-chain.
+    pub fn fee_convention(&self) -> FeeConvention {
+        let mint_fee_is_ata = fees::fee_amount(self.underlying_amount_per_contract) == 0
+            || self.mint_fee_account == get_associated_token_address(&fees::fee_owner_key::ID, &self.underlying_asset_mint);
+        let exercise_fee_is_ata = fees::fee_amount(self.quote_amount_per_contract) == 0
+            || self.exercise_fee_account == get_associated_token_address(&fees::fee_owner_key::ID, &self.quote_asset_mint);
+        if mint_fee_is_ata && exercise_fee_is_ata {
+            FeeConvention::AssociatedToken
+        } else {
+            FeeConvention::Raw
+        }
+    }
+
+    /// Whether the market has expired at `now`. Matches the checks on mint, exercise
+    /// and close: a market is still active during its expiration second. A negative
+    /// expiration is expired for any `now` from the epoch onwards.
+    pub fn is_expired(&self, now: UnixTimestamp) -> bool {
+        self.expiration_unix_timestamp < now
+    }
+
+    /// The `(underlying_out, quote_out)` released to a writer burning `burn_amount`
+    /// Writer Tokens after expiration. Each pool is scaled by
+    /// `burn_amount / writer_supply`, rounding down. A burn can't exceed the supply.
+    pub fn release_for_writer_burn(
+        &self,
+        burn_amount: u64,
+        pool_underlying: u64,
+        pool_quote: u64,
+        writer_supply: u64
+    ) -> Result<(u64, u64), ProgramError> {
+        if burn_amount > writer_supply {
+            return Err(errors::ErrorCode::BurnExceedsWriterSupply.into())
+        }
+        let underlying_out = pro_rata(pool_underlying, burn_amount, writer_supply)?;
+        let quote_out = pro_rata(pool_quote, burn_amount, writer_supply)?;
+        Ok((underlying_out, quote_out))
+    }
+
+    /// Post-condition check for an exercise of `contracts` contracts. The underlying
+    /// pool must have paid out, and the quote pool received, exactly the per contract
+    /// amounts times `contracts`.
+    ///
+    /// NOTE: `quote_in` is the change in the quote asset pool. The exercise fee is
+    /// transferred to the exercise fee account, so it is not part of this amount.
+    pub fn validate_exercise_delta(&self, underlying_out: u64, quote_in: u64, contracts: u64) -> Result<(), ProgramError> {
+        let expected_underlying = self.underlying_amount_per_contract.checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?;
+        let expected_quote = self.quote_amount_per_contract.checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?;
+        if underlying_out != expected_underlying || quote_in != expected_quote {
+            return Err(errors::ErrorCode::InvalidExerciseDelta.into())
+        }
+        Ok(())
+    }
+
+    /// Decode an OptionMarket whose serialized fields start at `offset` in `data`,
+    /// e.g. when it is embedded after a header in a larger account. The fields are
+    /// read without the Anchor account discriminator.
+    pub fn unpack_at(data: &[u8], offset: usize) -> Result<OptionMarket, ProgramError> {
+        let end = offset.checked_add(Self::LEN).ok_or(errors::ErrorCode::NumberOverflow)?;
+        if data.len() < end {
+            return Err(ProgramError::AccountDataTooSmall)
+        }
+        OptionMarket::try_from_slice(&data[offset..end]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// The amount of Option Tokens, in base units, that represent `contracts` contracts.
+    ///
+    /// One contract is one whole token, so this scales by `10^option_mint_decimals`.
+    /// Markets created by `initialize_market` use 0 decimals, in which case the
+    /// amount equals `contracts`.
+    pub fn option_tokens_for_contracts(&self, contracts: u64, option_mint_decimals: u8) -> Result<u64, ProgramError> {
+        Ok(contracts.checked_mul(token_scale(option_mint_decimals)?).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The amount of Writer Tokens, in base units, to mint when writing `contracts`
+    /// contracts. A writer receives one whole Writer Token per contract, the same
+    /// 1:1 relationship as Option Tokens, scaled by `10^writer_mint_decimals`.
+    pub fn writer_tokens_to_mint(&self, contracts: u64, writer_mint_decimals: u8) -> Result<u64, ProgramError> {
+        Ok(contracts.checked_mul(token_scale(writer_mint_decimals)?).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The accounts `initialize_market` expects, in the order they must be passed.
+    ///
+    /// The mint fee and exercise fee accounts follow these as remaining accounts,
+    /// and are only required when the respective fee is greater than 0.
+    pub fn init_account_layout() -> &'static [AccountRole] {
+        &INIT_ACCOUNT_LAYOUT
+    }
+
+    /// Whether the market can be garbage collected. This is true only when:
+    /// - more than `stale_after_secs` seconds have passed since expiration, and
+    /// - no Option Tokens remain (`option_supply == 0`), and
+    /// - no Writer Tokens remain (`writer_supply == 0`)
+    pub fn is_stale(&self, now: UnixTimestamp, option_supply: u64, writer_supply: u64, stale_after_secs: u64) -> bool {
+        let secs_since_expiration = now as i128 - self.expiration_unix_timestamp as i128;
+        secs_since_expiration > stale_after_secs as i128 && option_supply == 0 && writer_supply == 0
+    }
+
+    /// The quote asset pool balance expected after `contracts_exercised` contracts
+    /// have been exercised.
+    ///
+    /// NOTE: exercise fees are transferred to the exercise fee account and are not
+    /// included. This also assumes no writer has burned Writer Tokens for quote,
+    /// which withdraws from the pool.
+    pub fn expected_quote_pool(&self, contracts_exercised: u64) -> Result<u64, ProgramError> {
+        Ok(contracts_exercised.checked_mul(self.quote_amount_per_contract).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// Derive the OptionMarket's program address from its stored parameters and bump seed
+    pub fn market_address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(&self.signer_seeds().seeds(), program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Validate that a mint's authority (e.g. the Option or Writer Token mint) is the
+    /// OptionMarket PDA. Otherwise tokens could be minted without collateral.
+    pub fn validate_mint_authority(&self, mint_authority: &COption<Pubkey>, program_id: &Pubkey) -> Result<(), ProgramError> {
+        match mint_authority {
+            COption::Some(authority) if *authority == self.market_address(program_id)? => Ok(()),
+            _ => Err(errors::ErrorCode::OptionMarketMustBeMintAuthority.into())
+        }
+    }
+
+    /// A compact, human readable label for logs and UIs, e.g.
+    /// `CALL 100:5000 @50 exp=1640995200`.
+    ///
+    /// The amounts are the per contract asset and cash legs, followed by the strike
+    /// as the reduced cash per asset ratio (`@a/b` when it is not a whole number).
+    /// For a call the asset leg is the underlying; for a put it is the quote.
+    /// Mint addresses are left out to keep the label short.
+    pub fn label(&self, market_type: MarketType) -> String {
+        let (kind, asset_amount, cash_amount) = match market_type {
+            MarketType::Call => ("CALL", self.underlying_amount_per_contract, self.quote_amount_per_contract),
+            MarketType::Put => ("PUT", self.quote_amount_per_contract, self.underlying_amount_per_contract),
+        };
+        let strike = match reduce(cash_amount, asset_amount) {
+            (numerator, 1) => numerator.to_string(),
+            (numerator, denominator) => format!("{}/{}", numerator, denominator),
+        };
+        format!("{} {}:{} @{} exp={}", kind, asset_amount, cash_amount, strike, self.expiration_unix_timestamp)
+    }
+
+    /// Split `total_fee` between recipients by basis points. `recipient_bps` must sum
+    /// to 10,000. Each share rounds down and any remaining dust goes to the last
+    /// recipient, so the shares always add up to `total_fee`.
+    pub fn split_fee(&self, total_fee: u64, recipient_bps: &[u16]) -> Result<Vec<u64>, ProgramError> {
+        let total_bps: u32 = recipient_bps.iter().map(|bps| *bps as u32).sum();
+        if total_bps != 10_000 {
+            return Err(errors::ErrorCode::InvalidFeeSplit.into())
+        }
+        let mut shares = Vec::with_capacity(recipient_bps.len());
+        let mut distributed: u64 = 0;
+        for bps in &recipient_bps[..recipient_bps.len() - 1] {
+            let share = pro_rata(total_fee, *bps as u64, 10_000)?;
+            distributed += share;
+            shares.push(share);
+        }
+        shares.push(total_fee - distributed);
+        Ok(shares)
+    }
+
+    /// The exercise fee, in base units of the quote asset, charged for exercising
+    /// `contracts` contracts with `exercise_option`.
+    ///
+    /// NOTE: markets whose per contract fee rounds down to 0 charge
+    /// `NFT_MINT_LAMPORTS` in SOL instead, which is not included here.
+    pub fn exercise_fee_for(&self, contracts: u64) -> Result<u64, ProgramError> {
+        Ok(fees::fee_amount(self.quote_amount_per_contract).checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The total quote asset the exerciser must supply to exercise `contracts`
+    /// contracts: the quote paid into the pool plus the exercise fee.
+    pub fn quote_required_to_exercise(&self, contracts: u64) -> Result<u64, ProgramError> {
+        let quote = self.quote_amount_per_contract.checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?;
+        Ok(quote.checked_add(self.exercise_fee_for(contracts)?).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// Validate the exerciser's quote source account before transferring from it
+    pub fn validate_exercise_source(&self, src_mint: &Pubkey, src_balance: u64, contracts: u64) -> Result<(), ProgramError> {
+        if *src_mint != self.quote_asset_mint {
+            return Err(errors::ErrorCode::QuoteSrcMintDoesNotMatchQuoteAsset.into())
+        }
+        if src_balance < self.quote_required_to_exercise(contracts)? {
+            return Err(errors::ErrorCode::NotEnoughQuoteAssetsInSrc.into())
+        }
+        Ok(())
+    }
+
+    /// The amount of Option Tokens, in base units, to burn when exercising `contracts`
+    /// contracts. This is the same conversion used when minting, so a write and an
+    /// exercise of the same contracts always mint and burn the same amount.
+    pub fn option_tokens_to_burn(&self, contracts: u64, option_mint_decimals: u8) -> Result<u64, ProgramError> {
+        self.option_tokens_for_contracts(contracts, option_mint_decimals)
+    }
+
+    /// Whether the quote asset is in `allowlist`, e.g. a list of stablecoin mints
+    pub fn quote_is_in(&self, allowlist: &[Pubkey]) -> bool {
+        allowlist.contains(&self.quote_asset_mint)
+    }
+
+    /// What a holder gets and pays when exercising `contracts` contracts, as
+    /// `(underlying_received, total_quote_paid_including_fee)`.
+    ///
+    /// The underlying received is gross, no fee is taken from the underlying on
+    /// exercise. The quote paid includes the exercise fee charged by `exercise_option`.
+    pub fn exercise_net(&self, contracts: u64) -> Result<(u64, u64), ProgramError> {
+        let underlying_received = self.underlying_amount_per_contract.checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?;
+        Ok((underlying_received, self.quote_required_to_exercise(contracts)?))
+    }
+
+    /// Describe which pool and mints an exercise moves funds between.
+    ///
+    /// A put is written with the cash asset as the underlying and the asset being
+    /// sold as the quote, so for both market types the holder delivers the quote
+    /// asset and receives the underlying asset from the underlying pool.
+    pub fn exercise_flow(&self, market_type: MarketType) -> ExerciseFlow {
+        match market_type {
+            MarketType::Call | MarketType::Put => ExerciseFlow {
+                debited_pool: self.underlying_asset_pool,
+                credited_pool: self.quote_asset_pool,
+                holder_delivers_mint: self.quote_asset_mint,
+                holder_receives_mint: self.underlying_asset_mint,
+            },
+        }
+    }
+
+    /// Payouts `(underlying_out, quote_out)` for a sequence of Writer Token burns
+    /// settled against the same pools.
+    ///
+    /// Every burn is priced against the original `writer_supply` and pool balances
+    /// rather than the shrinking pools left after earlier burns, so the result does
+    /// not depend on the order of burns. The final burn also receives the rounding
+    /// dust, which keeps the total paid out equal to the pro-rata share of all
+    /// burns combined.
+    pub fn settlement_schedule(
+        &self,
+        burns: &[u64],
+        pool_underlying: u64,
+        pool_quote: u64,
+        writer_supply: u64
+    ) -> Result<Vec<(u64, u64)>, ProgramError> {
+        let total_burned = burns.iter().try_fold(0u64, |total, burn| total.checked_add(*burn))
+            .ok_or(errors::ErrorCode::NumberOverflow)?;
+        if total_burned > writer_supply {
+            return Err(errors::ErrorCode::BurnExceedsWriterSupply.into())
+        }
+        let mut schedule = Vec::with_capacity(burns.len());
+        let (mut underlying_paid, mut quote_paid) = (0u64, 0u64);
+        for (index, burn) in burns.iter().enumerate() {
+            let payout = if index == burns.len() - 1 {
+                let (underlying_total, quote_total) =
+                    self.release_for_writer_burn(total_burned, pool_underlying, pool_quote, writer_supply)?;
+                (underlying_total - underlying_paid, quote_total - quote_paid)
+            } else {
+                self.release_for_writer_burn(*burn, pool_underlying, pool_quote, writer_supply)?
+            };
+            underlying_paid += payout.0;
+            quote_paid += payout.1;
+            schedule.push(payout);
+        }
+        Ok(schedule)
+    }
+
+    /// The five economic parameters that define the market, suitable as a `HashMap`
+    /// key: `(underlying_asset_mint, quote_asset_mint, underlying_amount_per_contract,
+    /// quote_amount_per_contract, expiration_unix_timestamp)`.
+    ///
+    /// These are also the OptionMarket PDA seeds. The Option and Writer Token mints,
+    /// pools, fee accounts and the `expired` flag are excluded.
+    pub fn key_tuple(&self) -> (Pubkey, Pubkey, u64, u64, i64) {
+        (
+            self.underlying_asset_mint,
+            self.quote_asset_mint,
+            self.underlying_amount_per_contract,
+            self.quote_amount_per_contract,
+            self.expiration_unix_timestamp,
+        )
+    }
+
+    /// Validate the parameters stored on the market are sensible
+    pub fn validate_parameters(&self) -> Result<(), ProgramError> {
+        // UnixTimestamp is signed, but an expiration before the epoch is meaningless
+        if self.expiration_unix_timestamp < 0 {
+            return Err(errors::ErrorCode::InvalidExpiration.into())
+        }
+        if self.underlying_amount_per_contract == 0 || self.quote_amount_per_contract == 0 {
+            return Err(errors::ErrorCode::QuoteOrUnderlyingAmountCannotBe0.into())
+        }
+        if self.underlying_asset_mint == self.quote_asset_mint {
+            return Err(errors::ErrorCode::QuoteAndUnderlyingAssetMustDiffer.into())
+        }
+        if self.underlying_asset_pool == self.quote_asset_pool {
+            return Err(errors::ErrorCode::IdenticalPools.into())
+        }
+        let pools = [self.underlying_asset_pool, self.quote_asset_pool];
+        if pools.contains(&self.mint_fee_account) || pools.contains(&self.exercise_fee_account) {
+            return Err(errors::ErrorCode::FeeAccountIsPool.into())
+        }
+        Ok(())
+    }
+
+    /// The underlying asset needed to fully collateralize `option_supply` outstanding
+    /// contracts given the pool's current balance. Returns 0 when the pool already
+    /// holds enough, and saturates at `u64::MAX`.
+    pub fn required_topup(&self, pool_underlying: u64, option_supply: u64) -> u64 {
+        let required = option_supply as u128 * self.underlying_amount_per_contract as u128;
+        let shortfall = required.saturating_sub(pool_underlying as u128);
+        u64::try_from(shortfall).unwrap_or(u64::MAX)
+    }
+
+    /// The inclusive `(start, end)` timestamps during which `action` is allowed. A
+    /// `None` bound is unbounded.
+    ///
+    /// Minting and exercising are allowed up to and including the expiration
+    /// second, closing post expiration only after it. Closing a position and burning
+    /// Writer Tokens for quote are not time restricted.
+    pub fn valid_window(&self, action: MarketAction) -> (Option<UnixTimestamp>, Option<UnixTimestamp>) {
+        match action {
+            MarketAction::Mint | MarketAction::Exercise => (None, Some(self.expiration_unix_timestamp)),
+            MarketAction::ClosePostExpiration => (Some(self.expiration_unix_timestamp.saturating_add(1)), None),
+            MarketAction::ClosePosition | MarketAction::BurnWriterForQuote => (None, None),
+        }
+    }
+
+    /// The signed change in Option Token supply, in base units, from writing `written`
+    /// contracts and exercising `exercised` contracts. A write followed by an
+    /// exercise of the same contracts nets to 0.
+    pub fn net_supply_change(&self, written: u64, exercised: u64, decimals: u8) -> Result<i128, ProgramError> {
+        let minted = self.option_tokens_for_contracts(written, decimals)?;
+        let burned = self.option_tokens_to_burn(exercised, decimals)?;
+        Ok(minted as i128 - burned as i128)
+    }
+
+    /// The index of the week, counted from `epoch_start`, that the market expires in.
+    /// Weeks are 604,800 seconds long, and expirations before `epoch_start` land in
+    /// negative buckets (one second before `epoch_start` is bucket -1).
+    pub fn weekly_bucket(&self, epoch_start: UnixTimestamp) -> i64 {
+        let offset = self.expiration_unix_timestamp as i128 - epoch_start as i128;
+        offset.div_euclid(SECONDS_PER_WEEK as i128) as i64
+    }
+
+    /// The mint fee for `contracts` contracts split into `(whole_tokens,
+    /// fractional_base_units)` for display, where the whole part is in tokens of the
+    /// underlying asset and the fractional part is the remaining base units.
+    pub fn mint_fee_display(&self, contracts: u64, underlying_decimals: u8) -> Result<(u64, u64), ProgramError> {
+        let fee = self.mint_fee_for(contracts)?;
+        let scale = token_scale(underlying_decimals)?;
+        Ok((fee / scale, fee % scale))
+    }
+
+    /// Validate the quote asset pool holds at least what `contracts_exercised`
+    /// exercises should have paid in, before distributing it to writers
+    pub fn validate_quote_pool_for_settlement(&self, pool_quote: u64, contracts_exercised: u64) -> Result<(), ProgramError> {
+        if pool_quote < self.expected_quote_pool(contracts_exercised)? {
+            return Err(errors::ErrorCode::NotEnoughQuoteAssetsInPool.into())
+        }
+        Ok(())
+    }
+
+    /// Validate the mints of the pool token accounts match the market's assets
+    pub fn validate_pool_mints(&self, underlying_pool_mint: &Pubkey, quote_pool_mint: &Pubkey) -> Result<(), ProgramError> {
+        if *underlying_pool_mint != self.underlying_asset_mint {
+            return Err(errors::ErrorCode::UnderlyingPoolMintDoesNotMatchUnderlyingAsset.into())
+        }
+        if *quote_pool_mint != self.quote_asset_mint {
+            return Err(errors::ErrorCode::QuotePoolMintDoesNotMatchQuoteAsset.into())
+        }
+        Ok(())
+    }
+
+    /// The most contracts a holder can exercise right now: the smaller of their
+    /// balance and the number of contracts the underlying pool can still pay out
+    pub fn max_exercisable(&self, holder_balance_contracts: u64, pool_underlying: u64) -> u64 {
+        let pool_contracts = pool_underlying.checked_div(self.underlying_amount_per_contract).unwrap_or(0);
+        holder_balance_contracts.min(pool_contracts)
+    }
+
+    /// Time to expiration in years (of 365.25 days) for off-chain pricing models.
+    /// Returns 0 once the market has expired.
+    #[cfg(feature = "pricing")]
+    pub fn years_to_expiry(&self, now: UnixTimestamp) -> f64 {
+        let seconds = self.expiration_unix_timestamp.saturating_sub(now).max(0);
+        seconds as f64 / SECONDS_PER_YEAR
+    }
+
+    /// Validate a Writer Token mint passed in by the caller is this market's
+    pub fn validate_writer_mint(&self, mint: &Pubkey) -> Result<(), ProgramError> {
+        if *mint != self.writer_token_mint {
+            return Err(errors::ErrorCode::WriterTokenMintDoesNotMatchMarket.into())
+        }
+        Ok(())
+    }
+
+    /// The underlying pool's surplus over what is needed to back `option_supply`
+    /// outstanding contracts. Negative when the market is under-collateralized, and
+    /// saturates at `i128::MIN` when the requirement doesn't fit in an i128.
+    pub fn pool_health(&self, pool_underlying: u64, option_supply: u64) -> i128 {
+        let required = option_supply as u128 * self.underlying_amount_per_contract as u128;
+        i128::try_from(required).map_or(i128::MIN, |required| pool_underlying as i128 - required)
+    }
+
+    /// A writer's profit and loss on `contracts` contracts, in base units of the cash
+    /// asset: the total `premium_received` at write time minus what the holders
+    /// are owed at settlement. Positive is a profit for the writer, negative a loss.
+    ///
+    /// `settled_intrinsic` is the per contract intrinsic value at settlement, e.g.
+    /// `intrinsic_value(settlement_spot_price, market_type)`. A put is never worth
+    /// more than its strike, so its intrinsic is capped at `underlying_amount_per_contract`.
+    pub fn writer_return(
+        &self,
+        contracts: u64,
+        premium_received: u64,
+        market_type: MarketType,
+        settled_intrinsic: u64
+    ) -> Result<i128, ProgramError> {
+        let intrinsic = match market_type {
+            MarketType::Call => settled_intrinsic,
+            MarketType::Put => settled_intrinsic.min(self.underlying_amount_per_contract),
+        };
+        let payout = intrinsic.checked_mul(contracts).ok_or(errors::ErrorCode::NumberOverflow)?;
+        Ok(premium_received as i128 - payout as i128)
+    }
+
+    /// Validate a timestamp supplied by the caller is within `max_skew_secs` of the
+    /// cluster's clock (e.g. `Clock::get()?.unix_timestamp`), so expiry checks can't
+    /// be run against a stale or forged time.
+    pub fn require_clock(&self, provided_now: UnixTimestamp, actual_now: UnixTimestamp, max_skew_secs: i64) -> Result<(), ProgramError> {
+        let skew = (provided_now as i128 - actual_now as i128).abs();
+        if skew > max_skew_secs as i128 {
+            return Err(errors::ErrorCode::ClockSkew.into())
+        }
+        Ok(())
+    }
+
+    /// The quote a writer receives for burning `burn` of `writer_supply` Writer Tokens
+    /// against a quote pool holding `pool_quote`, rounding down.
+    ///
+    /// The dust is the base units that rounding down leaves unclaimed. When `is_last`
+    /// is set the burner instead takes `pool_quote` less the rounded down claim of the
+    /// other `writer_supply - burn` Writer Tokens, so the final burn sweeps the dust
+    /// and leaves no unclaimable balance behind, as the last payout of
+    /// `settlement_schedule` does. A burn can't exceed the supply.
+    pub fn quote_share_with_dust(&self, burn: u64, pool_quote: u64, writer_supply: u64, is_last: bool) -> Result<u64, ProgramError> {
+        if burn > writer_supply {
+            return Err(errors::ErrorCode::BurnExceedsWriterSupply.into())
+        }
+        if !is_last {
+            return pro_rata(pool_quote, burn, writer_supply)
+        }
+        Ok(pool_quote - pro_rata(pool_quote, writer_supply - burn, writer_supply)?)
+    }
+
+    /// The most contracts a writer holding `underlying_balance` can mint once the
+    /// mint fee is taken into account. Dividing the balance by
+    /// `underlying_amount_per_contract` alone overstates this whenever a fee is charged.
+    pub fn max_contracts_with_fee(&self, underlying_balance: u64) -> u64 {
+        let per_contract = self.underlying_amount_per_contract
+            .saturating_add(fees::fee_amount(self.underlying_amount_per_contract));
+        underlying_balance.checked_div(per_contract).unwrap_or(0)
+    }
+
+    /// Deserialize the market at `market_info` along with its two pools, returning
+    /// the market and the underlying and quote pool balances.
+    ///
+    /// Checks the market is owned by `program_id`, and that each pool is the SPL
+    /// Token account stored on the market, holds the expected mint and is owned by
+    /// the market.
+    pub fn load_with_pools(
+        market_info: &AccountInfo,
+        underlying_pool_info: &AccountInfo,
+        quote_pool_info: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<(OptionMarket, u64, u64), ProgramError> {
+        if market_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId)
+        }
+        let market = OptionMarket::try_deserialize(&mut &market_info.try_borrow_data()?[..])?;
+
+        if *underlying_pool_info.key != market.underlying_asset_pool {
+            return Err(errors::ErrorCode::UnderlyingPoolAccountDoesNotMatchMarket.into())
+        }
+        if *quote_pool_info.key != market.quote_asset_pool {
+            return Err(errors::ErrorCode::QuotePoolAccountDoesNotMatchMarket.into())
+        }
+        if underlying_pool_info.owner != &spl_token::ID || quote_pool_info.owner != &spl_token::ID {
+            return Err(errors::ErrorCode::ExpectedSPLTokenProgramId.into())
+        }
+        let underlying_pool = SPLTokenAccount::unpack_from_slice(&underlying_pool_info.try_borrow_data()?)?;
+        let quote_pool = SPLTokenAccount::unpack_from_slice(&quote_pool_info.try_borrow_data()?)?;
+        market.validate_pool_mints(&underlying_pool.mint, &quote_pool.mint)?;
+        if underlying_pool.owner != *market_info.key {
+            return Err(errors::ErrorCode::OptionMarketMustOwnUnderlyingAssetPool.into())
+        }
+        if quote_pool.owner != *market_info.key {
+            return Err(errors::ErrorCode::OptionMarketMustOwnQuoteAssetPool.into())
+        }
+        Ok((market, underlying_pool.amount, quote_pool.amount))
+    }
+
+    /// Lamports reclaimable when a fully settled market is torn down: the rent
+    /// exempt minimum of the market account, plus that of the two pool token accounts
+    /// when `include_pools` is set.
+    ///
+    /// Assumes every account holds exactly its rent exempt minimum, the market
+    /// account is `account_size()` bytes and each pool is `token_account_len` bytes
+    /// (`spl_token::state::Account::LEN` for the pools `initialize_market` creates).
+    /// The pools must be empty before they can be closed.
+    pub fn recoverable_rent(rent: &Rent, include_pools: bool, token_account_len: usize) -> u64 {
+        let market_rent = Self::rent_exempt_lamports(rent);
+        if !include_pools {
+            return market_rent
+        }
+        market_rent.saturating_add(rent.minimum_balance(token_account_len).saturating_mul(2))
+    }
+
+    /// Validate an Option Token mint passed in by the caller is this market's, so
+    /// only this market's Option Tokens can be burned to exercise against its pools.
+    pub fn validate_option_mint(&self, mint: &Pubkey) -> Result<(), ProgramError> {
+        if *mint != self.option_mint {
+            return Err(errors::ErrorCode::OptionTokenMintDoesNotMatchMarket.into())
+        }
+        Ok(())
+    }
+
+    /// Bundle the inputs for an off-chain pricing model. `spot` is the value of one
+    /// contract's asset leg in base units of the cash asset.
+    #[cfg(feature = "pricing")]
+    pub fn pricing_inputs(&self, now: UnixTimestamp, spot: f64, market_type: MarketType) -> PricingInputs {
+        let strike = match market_type {
+            MarketType::Call => self.quote_amount_per_contract,
+            MarketType::Put => self.underlying_amount_per_contract,
+        };
+        PricingInputs {
+            strike: strike as f64,
+            time_to_expiry_years: self.years_to_expiry(now),
+            is_call: market_type == MarketType::Call,
+            spot,
+        }
+    }
+
+    /// The share of all contracts ever written that are no longer outstanding, in
+    /// basis points. `total_written` is tracked by the caller since the market does
+    /// not store it. Returns 0 when nothing has been written.
+    ///
+    /// NOTE: positions closed with `close_option_position` also reduce the Option
+    /// Token supply, so these count as exercised here.
+    pub fn exercised_fraction_bps(&self, option_supply_remaining: u64, total_written: u64) -> u16 {
+        if total_written == 0 {
+            return 0
+        }
+        let exercised = total_written.saturating_sub(option_supply_remaining) as u128;
+        (exercised * 10_000 / total_written as u128) as u16
+    }
+
+    /// The seeds the OptionMarket signs CPIs with
+    pub fn signer_seeds(&self) -> SignerSeedBuf {
+        SignerSeedBuf {
+            underlying_asset_mint: self.underlying_asset_mint,
+            quote_asset_mint: self.quote_asset_mint,
+            underlying_amount_per_contract: self.underlying_amount_per_contract.to_le_bytes(),
+            quote_amount_per_contract: self.quote_amount_per_contract.to_le_bytes(),
+            expiration_unix_timestamp: self.expiration_unix_timestamp.to_le_bytes(),
+            bump_seed: [self.bump_seed],
+        }
+    }
+
+    /// Validate the exercise fee account holds the quote asset, the asset exercise
+    /// fees are paid in
+    pub fn validate_exercise_fee_account(&self, fee_account_mint: &Pubkey) -> Result<(), ProgramError> {
+        if *fee_account_mint != self.quote_asset_mint {
+            return Err(errors::ErrorCode::ExerciseFeeTokenMustMatchQuoteAsset.into())
+        }
+        Ok(())
+    }
+
+    /// An unmeasured estimate of the compute units `exercise_option` uses, for
+    /// clients setting a compute budget. It is built from rough per step costs
+    /// rather than program logs, and must be revisited when the exercise logic
+    /// changes.
+    ///
+    /// Covers account deserialization and validation plus four token or system
+    /// program CPIs: the Option Token burn, the quote and underlying transfers, and
+    /// the exercise fee (SOL for NFT markets).
+    pub fn estimated_exercise_cu(&self) -> u32 {
+        EXERCISE_BASE_CU + 4 * CPI_CU
+    }
+
+    /// Serialize the market fields into an owned array. Like `unpack_at`, this is
+    /// the raw Borsh encoding without the account discriminator.
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        self.serialize(&mut &mut bytes[..]).expect("OptionMarket::LEN matches the serialized size");
+        bytes
+    }
+
+    /// Deserialize market fields produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Result<OptionMarket, ProgramError> {
+        Self::unpack_at(bytes, 0)
+    }
+
+    /// Validate the market expires no more than `max_tenor_secs` after `now`.
+    /// A `max_tenor_secs` of 0 disables the check.
+    pub fn validate_max_tenor(&self, now: UnixTimestamp, max_tenor_secs: i64) -> Result<(), ProgramError> {
+        if max_tenor_secs == 0 {
+            return Ok(())
+        }
+        let tenor = self.expiration_unix_timestamp as i128 - now as i128;
+        if tenor > max_tenor_secs as i128 {
+            return Err(errors::ErrorCode::TenorTooLong.into())
+        }
+        Ok(())
+    }
+
+    /// The underlying collateral released to a writer closing `contracts` contracts
+    /// before expiration with `close_option_position`.
+    ///
+    /// NOTE: closing early requires burning an equal amount of Option Tokens and
+    /// Writer Tokens, so a writer must hold the Option Tokens for every contract closed.
+    pub fn early_close_return(&self, contracts: u64) -> Result<u64, ProgramError> {
+        Ok(contracts.checked_mul(self.underlying_amount_per_contract).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// Validate a pre-expiration close burns as many Option Tokens as Writer Tokens.
+    /// Once the market has expired writers settle through `close_post_expiration`
+    /// instead.
+    ///
+    /// NOTE: this is stricter than the program. `close_option_position` has no
+    /// expiry check and accepts closes after expiration, which is why `valid_window`
+    /// reports `ClosePosition` as unbounded. This is a client side policy check.
+    pub fn validate_early_close(&self, option_burn: u64, writer_burn: u64, now: UnixTimestamp) -> Result<(), ProgramError> {
+        if option_burn != writer_burn {
+            return Err(errors::ErrorCode::UnequalEarlyCloseBurns.into())
+        }
+        if self.is_expired(now) {
+            return Err(errors::ErrorCode::OptionMarketExpiredCantClosePosition.into())
+        }
+        Ok(())
+    }
+
+    /// Whether the quote pool holds a residual balance small enough to sweep:
+    /// more than 0 and at most `threshold` base units.
+    pub fn is_dust_pool(&self, pool_quote: u64, threshold: u64) -> bool {
+        pool_quote > 0 && pool_quote <= threshold
+    }
+
+    /// A short, stable identifier for correlating a market across logs: the 64-bit
+    /// FNV-1a hash of the option mint. This is not collision resistant and must not
+    /// be used for anything security related.
+    pub fn fingerprint(&self) -> u64 {
+        self.option_mint.as_ref().iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Validate both of the market's assets are listed in `registry`, an allowlist of
+    /// mints kept by the deployment. The underlying asset is checked first.
+    pub fn validate_against_registry(&self, registry: &[Pubkey]) -> Result<(), ProgramError> {
+        if !registry.contains(&self.underlying_asset_mint) {
+            return Err(errors::ErrorCode::UnderlyingAssetNotAllowed.into())
+        }
+        if !registry.contains(&self.quote_asset_mint) {
+            return Err(errors::ErrorCode::QuoteAssetNotAllowed.into())
+        }
+        Ok(())
+    }
+
+    /// The `(option_tokens, writer_tokens)`, in base units, a writer must hold to
+    /// close `contracts` contracts before expiration. Both are burned one whole
+    /// token per contract, scaled by `10^decimals`.
+    pub fn tokens_needed_for_unwind(&self, contracts: u64, decimals: u8) -> Result<(u64, u64), ProgramError> {
+        Ok((
+            self.option_tokens_for_contracts(contracts, decimals)?,
+            self.writer_tokens_to_mint(contracts, decimals)?,
+        ))
+    }
+
+    /// How far `spot_price` is from the strike, in basis points of the strike.
+    /// Positive when in the money, negative when out of the money and 0 at the money.
+    ///
+    /// Both are per contract amounts of the cash asset, as in `intrinsic_value`:
+    /// `(spot - strike) * 10_000 / strike` for a call and
+    /// `(strike - spot) * 10_000 / strike` for a put, truncated toward 0.
+    pub fn moneyness_bps(&self, spot_price: u64, market_type: MarketType) -> Result<i32, ProgramError> {
+        let bps = self.moneyness_bps_wide(spot_price, market_type).ok_or(errors::ErrorCode::DivideByZero)?;
+        Ok(i32::try_from(bps).map_err(|_| errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// `moneyness_bps` without narrowing to i32, or `None` when the strike is 0
+    fn moneyness_bps_wide(&self, spot_price: u64, market_type: MarketType) -> Option<i128> {
+        let (strike, distance) = match market_type {
+            MarketType::Call => {
+                let strike = self.quote_amount_per_contract as i128;
+                (strike, spot_price as i128 - strike)
+            },
+            MarketType::Put => {
+                let strike = self.underlying_amount_per_contract as i128;
+                (strike, strike - spot_price as i128)
+            },
+        };
+        if strike == 0 {
+            return None
+        }
+        Some(distance * 10_000 / strike)
+    }
+
+    /// The all-in price an exerciser pays per unit of underlying received, as the
+    /// reduced ratio `(quote_paid, underlying_received)`. The quote paid includes
+    /// the exercise fee, so this is above the strike whenever a fee is charged.
+    pub fn effective_exercise_price(&self, contracts: u64) -> Result<(u64, u64), ProgramError> {
+        if contracts == 0 {
+            return Err(errors::ErrorCode::SizeCantBeLessThanEqZero.into())
+        }
+        let quote_paid = self.quote_required_to_exercise(contracts)?;
+        let underlying_received = self.total_notional(contracts)?;
+        Ok(reduce(quote_paid, underlying_received))
+    }
+
+    /// Validate the market expires at least `min_tenor_secs` after `now`
+    pub fn validate_min_tenor(&self, now: UnixTimestamp, min_tenor_secs: i64) -> Result<(), ProgramError> {
+        let tenor = self.expiration_unix_timestamp as i128 - now as i128;
+        if tenor < min_tenor_secs as i128 {
+            return Err(errors::ErrorCode::TenorTooShort.into())
+        }
+        Ok(())
+    }
+
+    /// The quote pool balance after exercising each amount of contracts in `exercises`
+    /// against an empty pool, under the same assumptions as `expected_quote_pool`
+    pub fn simulate_quote_pool(&self, exercises: &[u64]) -> Result<u64, ProgramError> {
+        exercises.iter().try_fold(0u64, |pool, contracts| {
+            Ok(pool.checked_add(self.expected_quote_pool(*contracts)?).ok_or(errors::ErrorCode::NumberOverflow)?)
+        })
+    }
+
+    /// The spot price at which a holder who paid `premium_per_contract` breaks even
+    /// at exercise, as the reduced ratio of cash asset per unit of the asset leg.
+    ///
+    /// For a call this is `(quote_amount_per_contract + premium) / underlying_amount_per_contract`.
+    /// For a put it is `(underlying_amount_per_contract - premium) / quote_amount_per_contract`,
+    /// which is 0 when the premium exceeds the strike.
+    pub fn break_even_ratio(&self, premium_per_contract: u64, market_type: MarketType) -> Result<(u64, u64), ProgramError> {
+        let (cash, asset) = match market_type {
+            MarketType::Call => (
+                self.quote_amount_per_contract.checked_add(premium_per_contract).ok_or(errors::ErrorCode::NumberOverflow)?,
+                self.underlying_amount_per_contract,
+            ),
+            MarketType::Put => (
+                self.underlying_amount_per_contract.saturating_sub(premium_per_contract),
+                self.quote_amount_per_contract,
+            ),
+        };
+        if asset == 0 {
+            return Err(errors::ErrorCode::DivideByZero.into())
+        }
+        Ok(reduce(cash, asset))
+    }
+
+    /// Validate the market account was passed as writable before mutating it
+    pub fn require_writable(account: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_writable {
+            return Err(ProgramError::InvalidArgument)
+        }
+        Ok(())
+    }
+
+    /// The part of `charged_fee` to refund when only `contracts_completed` of
+    /// `contracts_intended` contracts were written, rounding down.
+    ///
+    /// A write either completes or the whole transaction fails, so this should not
+    /// normally be needed. It supports recovery paths that charge the fee up front.
+    pub fn fee_refund(&self, charged_fee: u64, contracts_completed: u64, contracts_intended: u64) -> Result<u64, ProgramError> {
+        let uncompleted = contracts_intended.saturating_sub(contracts_completed);
+        pro_rata(charged_fee, uncompleted, contracts_intended)
+    }
+
+    /// The underlying exposure of one contract given a `delta` computed off-chain,
+    /// in base units of the underlying asset
+    #[cfg(feature = "pricing")]
+    pub fn hedge_ratio(&self, delta: f64) -> f64 {
+        delta * self.underlying_amount_per_contract as f64
+    }
+
+    /// Whether the market expires at or before `deadline`
+    pub fn expires_before(&self, deadline: UnixTimestamp) -> bool {
+        self.expiration_unix_timestamp <= deadline
+    }
+
+    /// Validate the market expires at or before `deadline`, e.g. when bundling
+    /// markets into a product whose legs must all expire by a common date
+    pub fn validate_expires_before(&self, deadline: UnixTimestamp) -> Result<(), ProgramError> {
+        if !self.expires_before(deadline) {
+            return Err(errors::ErrorCode::ExpiryAfterDeadline.into())
+        }
+        Ok(())
+    }
+
+    /// The quote asset paid into the quote pool for each contract exercised.
+    ///
+    /// NOTE: exercises aren't assigned to individual writers. The quote pool is
+    /// shared by all Writer Tokens, so a writer's actual share depends on the pool and
+    /// Writer Token supply when they burn (see `release_for_writer_burn`).
+    pub fn writer_quote_per_assigned_contract(&self) -> u64 {
+        self.quote_amount_per_contract
+    }
+
+    /// The quote asset paid into the quote pool for `assigned` exercised contracts
+    pub fn writer_quote_for_assigned(&self, assigned: u64) -> Result<u64, ProgramError> {
+        Ok(self.writer_quote_per_assigned_contract().checked_mul(assigned).ok_or(errors::ErrorCode::NumberOverflow)?)
+    }
+
+    /// The most Option Tokens, in base units, that `pool_underlying` can fully back.
+    /// Partial contracts' worth of underlying in the pool back no tokens.
+    pub fn max_backed_option_tokens(&self, pool_underlying: u64, decimals: u8) -> Result<u64, ProgramError> {
+        let contracts = pool_underlying.checked_div(self.underlying_amount_per_contract).ok_or(errors::ErrorCode::DivideByZero)?;
+        self.option_tokens_for_contracts(contracts, decimals)
+    }
+
+    /// Validate the Option Token mint has at most `max_decimals` decimals
+    pub fn validate_option_decimals(&self, option_mint_decimals: u8, max_decimals: u8) -> Result<(), ProgramError> {
+        if option_mint_decimals > max_decimals {
+            return Err(errors::ErrorCode::UnsupportedDecimals.into())
+        }
+        Ok(())
+    }
+
+    /// The underlying a writer holding `writer_balance` Writer Tokens can reclaim
+    /// from a pool holding `pool_underlying`, their pro rata share of `writer_supply`
+    /// rounded down. Returns 0 when `writer_supply` is 0. A balance can't exceed
+    /// the supply.
+    pub fn writer_underlying_claim(&self, writer_balance: u64, pool_underlying: u64, writer_supply: u64) -> Result<u64, ProgramError> {
+        if writer_balance > writer_supply {
+            return Err(errors::ErrorCode::BurnExceedsWriterSupply.into())
+        }
+        if writer_supply == 0 {
+            return Ok(0)
+        }
+        pro_rata(pool_underlying, writer_balance, writer_supply)
+    }
+
+    /// The market's lifecycle phase at `now`. American options can be exercised at
+    /// any time before expiration, so there is no separate exercise only phase.
+    pub fn phase(&self, now: UnixTimestamp) -> MarketPhase {
+        if self.is_expired(now) {
+            MarketPhase::Expired
+        } else {
+            MarketPhase::Active
+        }
+    }
+
+    /// Validate the strike, `quote_amount_per_contract / underlying_amount_per_contract`,
+    /// lies within the inclusive range `[min, max]`, where each bound is a
+    /// `(numerator, denominator)` ratio. Comparisons are exact, by cross-multiplying.
+    ///
+    /// This is meant to catch amounts entered with the wrong decimals at creation.
+    pub fn strike_sanity(&self, min: (u64, u64), max: (u64, u64)) -> Result<(), ProgramError> {
+        if min.1 == 0 || max.1 == 0 {
+            return Err(errors::ErrorCode::DivideByZero.into())
+        }
+        let quote = self.quote_amount_per_contract as u128;
+        let underlying = self.underlying_amount_per_contract as u128;
+        let above_min = quote * min.1 as u128 >= min.0 as u128 * underlying;
+        let below_max = quote * max.1 as u128 <= max.0 as u128 * underlying;
+        if !above_min || !below_max {
+            return Err(errors::ErrorCode::StrikeOutOfRange.into())
+        }
+        Ok(())
+    }
+
+    /// Split a raw Option Token amount into `(whole_contracts, leftover_base_units)`,
+    /// where one contract is `10^decimals` base units
+    pub fn contracts_and_remainder(&self, raw_amount: u64, decimals: u8) -> Result<(u64, u64), ProgramError> {
+        let scale = token_scale(decimals)?;
+        Ok((raw_amount / scale, raw_amount % scale))
+    }
+
+    /// Preflight the accounts passed to `initialize_market`, in the order given by
+    /// `init_account_layout`.
+    ///
+    /// Checks there are enough accounts, that the signer and writable accounts are
+    /// marked as such, and each account's `AccountCheck`: the fee owner, program and
+    /// sysvar accounts are the expected ones, every account `init` creates doesn't
+    /// exist yet, and the mints and pools are at the addresses `program_id` derives
+    /// from the market.
+    ///
+    /// NOTE: this is a helper for SDKs and other off-chain callers to catch ordering
+    /// mistakes before sending a transaction. `initialize_market` relies on its
+    /// Accounts constraints instead and doesn't call it.
+    pub fn validate_init_accounts(accounts: &[AccountInfo], program_id: &Pubkey) -> Result<(), ProgramError> {
+        instructions::require_account_count(accounts, INIT_ACCOUNT_LAYOUT.len())?;
+        let option_market = accounts[INIT_OPTION_MARKET_INDEX].key;
+        for (account, role) in accounts.iter().zip(INIT_ACCOUNT_LAYOUT.iter()) {
+            if role.is_signer && !account.is_signer {
+                return Err(ProgramError::MissingRequiredSignature)
+            }
+            if role.is_writable {
+                Self::require_writable(account)?;
+            }
+            role.check.validate(account, option_market, program_id)?;
+        }
+        Ok(())
+    }
+
+    /// Classify the market for margining at `now` given `spot` (per contract, as in
+    /// `intrinsic_value`).
+    ///
+    /// A market is near when it expires within 30 days of `now`, which includes
+    /// expired markets, and far otherwise. It is at the money when `moneyness_bps`
+    /// is within 500 bps (5%) of the strike either way, otherwise in or out of the
+    /// money by its sign. A market with a zero strike counts as at the money.
+    pub fn risk_bucket(&self, now: UnixTimestamp, spot: u64, market_type: MarketType) -> RiskBucket {
+        let near = (self.expiration_unix_timestamp as i128 - now as i128) <= NEAR_EXPIRY_SECS as i128;
+        let bps = self.moneyness_bps_wide(spot, market_type).unwrap_or(0);
+        match (near, bps) {
+            (true, bps) if bps.abs() <= AT_THE_MONEY_BPS => RiskBucket::NearAtTheMoney,
+            (true, bps) if bps > 0 => RiskBucket::NearInTheMoney,
+            (true, _) => RiskBucket::NearOutOfTheMoney,
+            (false, bps) if bps.abs() <= AT_THE_MONEY_BPS => RiskBucket::FarAtTheMoney,
+            (false, bps) if bps > 0 => RiskBucket::FarInTheMoney,
+            (false, _) => RiskBucket::FarOutOfTheMoney,
+        }
+    }
+
+    /// The number of whole contracts in `raw_option_amount`, validating it doesn't
+    /// include a fraction of a contract
+    pub fn validate_contract_alignment(&self, raw_option_amount: u64, decimals: u8) -> Result<u64, ProgramError> {
+        match self.contracts_and_remainder(raw_option_amount, decimals)? {
+            (contracts, 0) => Ok(contracts),
+            _ => Err(errors::ErrorCode::UnalignedExercise.into()),
+        }
+    }
+}
+
+/// Filter `markets` down to the ones that have not expired at `now`
+pub fn filter_active(markets: &[OptionMarket], now: UnixTimestamp) -> Vec<&OptionMarket> {
+    markets.iter().filter(|market| !market.is_expired(now)).collect()
+}
+
+/// Find markets with identical economics, compared by `OptionMarket::key_tuple`.
+/// Returns each colliding pair of indices `(i, j)` with `i < j`.
+pub fn find_duplicates(markets: &[OptionMarket]) -> Vec<(usize, usize)> {
+    let mut duplicates = Vec::new();
+    for (i, market) in markets.iter().enumerate() {
+        for (j, other) in markets.iter().enumerate().skip(i + 1) {
+            if market.key_tuple() == other.key_tuple() {
+                duplicates.push((i, j));
+            }
+        }
+    }
+    duplicates
+}
+
+/// The at-the-money market of an option chain: the one whose strike is closest to
+/// `spot_price`. Ties go to the lower strike.
+///
+/// Markets are read as calls and are expected to share an underlying, quote,
+/// expiration and `underlying_amount_per_contract`, so `spot_price` is the value of
+/// one contract's underlying in quote base units, the same convention
+/// `intrinsic_value` uses. Returns `None` when the chain has no market with a
+/// non zero contract size.
+pub fn nearest_atm(markets: &[OptionMarket], spot_price: u64) -> Option<&OptionMarket> {
+    let contract_size = markets.iter()
+        .map(|market| market.underlying_amount_per_contract)
+        .find(|size| *size != 0)?;
+    nearest_atm_ratio(markets, (spot_price, contract_size))
+}
+
+/// `nearest_atm` for chains whose markets use different contract sizes, where no
+/// single per contract spot price applies.
+///
+/// `spot` is the price of one base unit of the underlying as the ratio `(quote,
+/// underlying)`, like the strike `quote_amount_per_contract /
+/// underlying_amount_per_contract`. Distances are compared exactly by
+/// cross-multiplying. Returns `None` when the chain is empty or the spot's
+/// denominator is 0.
+pub fn nearest_atm_ratio(markets: &[OptionMarket], spot: (u64, u64)) -> Option<&OptionMarket> {
+    let (spot_quote, spot_underlying) = spot;
+    if spot_underlying == 0 {
+        return None
+    }
+    // `|quote / underlying - spot_quote / spot_underlying|` scaled by `spot_underlying`
+    // is `distance / underlying`, which is compared as the pair below
+    let distance = |market: &OptionMarket| {
+        let lhs = market.quote_amount_per_contract as u128 * spot_underlying as u128;
+        let rhs = spot_quote as u128 * market.underlying_amount_per_contract as u128;
+        (lhs.max(rhs) - lhs.min(rhs), market.underlying_amount_per_contract)
+    };
+    let distance_cmp = |a: &OptionMarket, b: &OptionMarket| {
+        let (a_distance, a_underlying) = distance(a);
+        let (b_distance, b_underlying) = distance(b);
+        wide_mul(a_distance, b_underlying).cmp(&wide_mul(b_distance, a_underlying))
+    };
+    // Compare strikes exactly by cross-multiplying quote * underlying
+    let strike_cmp = |a: &OptionMarket, b: &OptionMarket| {
+        let lhs = a.quote_amount_per_contract as u128 * b.underlying_amount_per_contract as u128;
+        let rhs = b.quote_amount_per_contract as u128 * a.underlying_amount_per_contract as u128;
+        lhs.cmp(&rhs)
+    };
+    markets.iter()
+        .filter(|market| market.underlying_amount_per_contract != 0)
+        .min_by(|a, b| distance_cmp(a, b).then_with(|| strike_cmp(a, b)))
+}
+
+/// The underlying collateral a writer has locked across `positions`, each a market
+/// and the number of contracts written in it. Collateral is summed in base units
+/// regardless of mint, so callers should group positions by underlying asset.
+pub fn writer_total_collateral(positions: &[(OptionMarket, u64)]) -> Result<u64, ProgramError> {
+    positions.iter().try_fold(0u64, |total, (market, contracts)| {
+        Ok(total.checked_add(market.early_close_return(*contracts)?).ok_or(errors::ErrorCode::NumberOverflow)?)
+    })
+}
+
+/// The notional weighted average strike of `positions`, each a market and a number
+/// of contracts, as the reduced ratio `(quote, underlying)`.
+///
+/// Weighting each strike by its underlying notional makes this the total quote
+/// across all positions over their total underlying. For markets with the same
+/// `underlying_amount_per_contract` this is the contract weighted average.
+pub fn weighted_avg_strike(positions: &[(OptionMarket, u64)]) -> Result<(u64, u64), ProgramError> {
+    let (quote, underlying) = positions.iter().fold((0u128, 0u128), |(quote, underlying), (market, contracts)| {
+        (
+            quote.saturating_add(market.quote_amount_per_contract as u128 * *contracts as u128),
+            underlying.saturating_add(market.underlying_amount_per_contract as u128 * *contracts as u128),
+        )
+    });
+    if underlying == 0 {
+        return Err(errors::ErrorCode::DivideByZero.into())
+    }
+    let quote = u64::try_from(quote).map_err(|_| errors::ErrorCode::NumberOverflow)?;
+    let underlying = u64::try_from(underlying).map_err(|_| errors::ErrorCode::NumberOverflow)?;
+    Ok(reduce(quote, underlying))
+}
+
+/// Calculate `amount * numerator / denominator` rounding down, without overflowing
+/// the intermediate product
+fn pro_rata(amount: u64, numerator: u64, denominator: u64) -> Result<u64, ProgramError> {
+    if denominator == 0 {
+        return Err(errors::ErrorCode::DivideByZero.into())
+    }
+    let scaled = (amount as u128) * (numerator as u128) / (denominator as u128);
+    Ok(u64::try_from(scaled).map_err(|_| errors::ErrorCode::NumberOverflow)?)
+}
+
+/// The full 192 bit product `a * b` as `(high, low)` 128 bit halves, which order the
+/// same way as the product
+fn wide_mul(a: u128, b: u64) -> (u128, u128) {
+    let low_product = (a as u64 as u128) * b as u128;
+    let high_product = (a >> 64) * b as u128;
+    let (low, carry) = low_product.overflowing_add(high_product << 64);
+    ((high_product >> 64) + carry as u128, low)
+}
+
+/// Reduce the fraction `numerator / denominator` to its lowest terms
+fn reduce(numerator: u64, denominator: u64) -> (u64, u64) {
+    let divisor = gcd(numerator, denominator);
+    if divisor == 0 {
+        return (numerator, denominator)
+    }
+    (numerator / divisor, denominator / divisor)
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// The number of base units in one whole token with `decimals` decimals
+fn token_scale(decimals: u8) -> Result<u64, ProgramError> {
+    Ok(10u64.checked_pow(decimals as u32).ok_or(errors::ErrorCode::NumberOverflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    /// A call on 1,000,000 base units of underlying struck at 50 quote per unit
+    fn market() -> OptionMarket {
+        OptionMarket {
+            option_mint: key(1),
+            writer_token_mint: key(2),
+            underlying_asset_mint: key(3),
+            quote_asset_mint: key(4),
+            underlying_amount_per_contract: 1_000_000,
+            quote_amount_per_contract: 50_000_000,
+            expiration_unix_timestamp: 1_640_995_200,
+            underlying_asset_pool: key(5),
+            quote_asset_pool: key(6),
+            mint_fee_account: key(7),
+            exercise_fee_account: key(8),
+            expired: false,
+            bump_seed: 0,
+        }
+    }
+
+    fn error(code: errors::ErrorCode) -> ProgramError {
+        code.into()
+    }
+
+    /// `market()` with the bump seed of its program address under `crate::ID`
+    fn derived_market() -> (OptionMarket, Pubkey) {
+        let mut market = market();
+        let (address, bump_seed) = Pubkey::find_program_address(
+            &[
+                market.underlying_asset_mint.as_ref(),
+                market.quote_asset_mint.as_ref(),
+                &market.underlying_amount_per_contract.to_le_bytes(),
+                &market.quote_amount_per_contract.to_le_bytes(),
+                &market.expiration_unix_timestamp.to_le_bytes(),
+            ],
+            &crate::ID,
+        );
+        market.bump_seed = bump_seed;
+        (market, address)
+    }
+
+    #[test]
+    fn realized_strike_at_contract_strike() {
+        let market = market();
+        assert_eq!(market.realized_strike(50_000_000, 1_000_000).unwrap(), (50, 1));
+        assert_eq!(market.realized_strike(150_000_000, 3_000_000).unwrap(), (50, 1));
+        assert_eq!(market.realized_strike(3, 2).unwrap(), (3, 2));
+    }
+
+    #[test]
+    fn realized_strike_rejects_zero_underlying() {
+        assert_eq!(market().realized_strike(50_000_000, 0).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+    }
+
+    #[test]
+    fn total_notional_scales_by_contracts() {
+        let market = market();
+        assert_eq!(market.notional_per_contract(), 1_000_000);
+        assert_eq!(market.total_notional(0).unwrap(), 0);
+        assert_eq!(market.total_notional(7).unwrap(), 7_000_000);
+    }
+
+    #[test]
+    fn total_notional_overflow() {
+        assert_eq!(market().total_notional(u64::MAX).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn fee_convention_associated_token() {
+        let mut market = market();
+        market.mint_fee_account = get_associated_token_address(&fees::fee_owner_key::ID, &market.underlying_asset_mint);
+        market.exercise_fee_account = get_associated_token_address(&fees::fee_owner_key::ID, &market.quote_asset_mint);
+        assert_eq!(market.fee_convention(), FeeConvention::AssociatedToken);
+
+        // Fees that round down to 0 store the fee owner itself, which doesn't count
+        let mut no_fees = market.clone();
+        no_fees.underlying_amount_per_contract = 1;
+        no_fees.quote_amount_per_contract = 1;
+        no_fees.mint_fee_account = fees::fee_owner_key::ID;
+        no_fees.exercise_fee_account = fees::fee_owner_key::ID;
+        assert_eq!(no_fees.fee_convention(), FeeConvention::AssociatedToken);
+    }
+
+    #[test]
+    fn fee_convention_raw() {
+        let mut market = market();
+        assert_eq!(market.fee_convention(), FeeConvention::Raw);
+
+        market.mint_fee_account = get_associated_token_address(&fees::fee_owner_key::ID, &market.underlying_asset_mint);
+        assert_eq!(market.fee_convention(), FeeConvention::Raw);
+    }
+
+    #[test]
+    fn validate_write_source_checks_mint_and_balance() {
+        let market = market();
+        // 2 contracts need 2,000,000 of collateral plus a 500 per contract mint fee
+        assert_eq!(market.validate_write_source(&market.underlying_asset_mint, 2_001_000, 2), Ok(()));
+        assert_eq!(
+            market.validate_write_source(&market.underlying_asset_mint, 2_000_999, 2).unwrap_err(),
+            error(errors::ErrorCode::NotEnoughUnderlyingAssetsInSrc)
+        );
+        assert_eq!(
+            market.validate_write_source(&market.quote_asset_mint, 2_001_000, 2).unwrap_err(),
+            error(errors::ErrorCode::UnderlyingSrcMintDoesNotMatchUnderlyingAsset)
+        );
+    }
+
+    #[test]
+    fn intrinsic_and_time_value() {
+        let market = market();
+        // In the money call and put
+        assert_eq!(market.intrinsic_value(60_000_000, MarketType::Call), 10_000_000);
+        assert_eq!(market.time_value(12_000_000, 60_000_000, MarketType::Call), Ok(2_000_000));
+        assert_eq!(market.intrinsic_value(400_000, MarketType::Put), 600_000);
+        assert_eq!(market.time_value(700_000, 400_000, MarketType::Put), Ok(100_000));
+        // At the money the whole premium is time value
+        assert_eq!(market.intrinsic_value(50_000_000, MarketType::Call), 0);
+        assert_eq!(market.time_value(3_000_000, 50_000_000, MarketType::Call), Ok(3_000_000));
+        // A premium below intrinsic has no time value
+        assert_eq!(market.time_value(9_000_000, 60_000_000, MarketType::Call), Ok(0));
+    }
+
+    #[test]
+    fn is_expired_after_expiration_second() {
+        let market = market();
+        assert!(!market.is_expired(market.expiration_unix_timestamp - 1));
+        assert!(!market.is_expired(market.expiration_unix_timestamp));
+        assert!(market.is_expired(market.expiration_unix_timestamp + 1));
+    }
+
+    #[test]
+    fn filter_active_drops_expired_markets() {
+        let now = market().expiration_unix_timestamp;
+        let mut expired = market();
+        expired.expiration_unix_timestamp = now - 1;
+        let mut later = market();
+        later.expiration_unix_timestamp = now + 1;
+        let markets = [expired, market(), later];
+
+        let active: Vec<i64> = filter_active(&markets, now).iter().map(|m| m.expiration_unix_timestamp).collect();
+        assert_eq!(active, vec![now, now + 1]);
+        assert!(filter_active(&markets, now + 2).is_empty());
+    }
+
+    #[test]
+    fn release_for_writer_burn_across_pools() {
+        let market = market();
+        // Nothing exercised, only underlying left
+        assert_eq!(market.release_for_writer_burn(3, 10_000_000, 0, 10).unwrap(), (3_000_000, 0));
+        // Everything exercised, only quote left
+        assert_eq!(market.release_for_writer_burn(3, 0, 500_000_000, 10).unwrap(), (0, 150_000_000));
+        // Partially exercised, rounding each pool down
+        assert_eq!(market.release_for_writer_burn(1, 7_000_001, 150_000_002, 10).unwrap(), (700_000, 15_000_000));
+        // Burning the whole supply releases both pools in full
+        assert_eq!(market.release_for_writer_burn(10, 7_000_001, 150_000_002, 10).unwrap(), (7_000_001, 150_000_002));
+    }
+
+    #[test]
+    fn release_for_writer_burn_rejects_bad_supply() {
+        let market = market();
+        assert_eq!(market.release_for_writer_burn(0, 100, 50, 0).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+        assert_eq!(
+            market.release_for_writer_burn(10, 100, 50, 5).unwrap_err(),
+            error(errors::ErrorCode::BurnExceedsWriterSupply)
+        );
+    }
+
+    #[test]
+    fn validate_exercise_delta_matches_contracts() {
+        let market = market();
+        assert_eq!(market.validate_exercise_delta(3_000_000, 150_000_000, 3), Ok(()));
+        assert_eq!(
+            market.validate_exercise_delta(3_000_001, 150_000_000, 3).unwrap_err(),
+            error(errors::ErrorCode::InvalidExerciseDelta)
+        );
+        assert_eq!(
+            market.validate_exercise_delta(3_000_000, 149_999_999, 3).unwrap_err(),
+            error(errors::ErrorCode::InvalidExerciseDelta)
+        );
+        assert_eq!(
+            market.validate_exercise_delta(0, 0, u64::MAX).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+    }
+
+    #[test]
+    fn unpack_at_after_header() {
+        let market = market();
+        let mut data = vec![0xffu8; 16];
+        data.extend(market.try_to_vec().unwrap());
+        assert_eq!(data.len(), 16 + OptionMarket::LEN);
+
+        let unpacked = OptionMarket::unpack_at(&data, 16).unwrap();
+        assert_eq!(unpacked.try_to_vec().unwrap(), market.try_to_vec().unwrap());
+    }
+
+    #[test]
+    fn unpack_at_too_short() {
+        let mut data = vec![0u8; 16];
+        data.extend(market().try_to_vec().unwrap());
+        data.pop();
+        assert_eq!(OptionMarket::unpack_at(&data, 16).err(), Some(ProgramError::AccountDataTooSmall));
+        assert_eq!(OptionMarket::unpack_at(&data, usize::MAX).err(), Some(error(errors::ErrorCode::NumberOverflow)));
+    }
+
+    #[test]
+    fn token_amounts_across_decimals() {
+        let market = market();
+        assert_eq!(market.option_tokens_for_contracts(3, 0).unwrap(), 3);
+        assert_eq!(market.option_tokens_for_contracts(3, 6).unwrap(), 3_000_000);
+        assert_eq!(market.writer_tokens_to_mint(3, 0).unwrap(), 3);
+        assert_eq!(market.writer_tokens_to_mint(3, 9).unwrap(), 3_000_000_000);
+    }
+
+    #[test]
+    fn token_amounts_overflow() {
+        let market = market();
+        assert_eq!(market.option_tokens_for_contracts(u64::MAX, 1).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+        assert_eq!(market.writer_tokens_to_mint(1, 20).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn init_account_layout_roles() {
+        let layout = OptionMarket::init_account_layout();
+        assert_eq!(layout[INIT_OPTION_MARKET_INDEX].name, "option_market");
+        assert_eq!(layout.len(), 14);
+        assert_eq!(layout[0], role("authority", true, true, AccountCheck::Any));
+        assert_eq!(layout[7], role("option_market", false, true, AccountCheck::Uninitialized));
+        assert_eq!(layout[13], role("clock", false, false, AccountCheck::Clock));
+        assert_eq!(layout.iter().filter(|role| role.is_signer).count(), 1);
+    }
+
+    #[test]
+    fn is_stale_after_threshold() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        assert!(!market.is_stale(expiration + 100, 0, 0, 100));
+        assert!(market.is_stale(expiration + 101, 0, 0, 100));
+        // Outstanding tokens keep a market alive
+        assert!(!market.is_stale(expiration + 101, 1, 0, 100));
+        assert!(!market.is_stale(expiration + 101, 0, 1, 100));
+    }
+
+    #[test]
+    fn expected_quote_pool_scales_by_exercises() {
+        let market = market();
+        assert_eq!(market.expected_quote_pool(0).unwrap(), 0);
+        assert_eq!(market.expected_quote_pool(4).unwrap(), 200_000_000);
+    }
+
+    #[test]
+    fn expected_quote_pool_overflow() {
+        let market = market();
+        let max_contracts = u64::MAX / market.quote_amount_per_contract;
+        assert_eq!(market.expected_quote_pool(max_contracts).unwrap(), max_contracts * 50_000_000);
+        assert_eq!(market.expected_quote_pool(max_contracts + 1).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn validate_mint_authority_requires_market_pda() {
+        let (market, address) = derived_market();
+        assert_eq!(market.market_address(&crate::ID).unwrap(), address);
+        assert_eq!(market.validate_mint_authority(&COption::Some(address), &crate::ID), Ok(()));
+        assert_eq!(
+            market.validate_mint_authority(&COption::Some(key(9)), &crate::ID).unwrap_err(),
+            error(errors::ErrorCode::OptionMarketMustBeMintAuthority)
+        );
+        assert_eq!(
+            market.validate_mint_authority(&COption::None, &crate::ID).unwrap_err(),
+            error(errors::ErrorCode::OptionMarketMustBeMintAuthority)
+        );
+    }
+
+    #[test]
+    fn label_format() {
+        let mut market = market();
+        assert_eq!(market.label(MarketType::Call), "CALL 1000000:50000000 @50 exp=1640995200");
+        assert_eq!(market.label(MarketType::Put), "PUT 50000000:1000000 @1/50 exp=1640995200");
+
+        market.underlying_amount_per_contract = 3;
+        market.quote_amount_per_contract = 10;
+        assert_eq!(market.label(MarketType::Call), "CALL 3:10 @10/3 exp=1640995200");
+    }
+
+    #[test]
+    fn split_fee_two_ways() {
+        assert_eq!(market().split_fee(1_000, &[7_000, 3_000]).unwrap(), vec![700, 300]);
+        assert_eq!(market().split_fee(1_000, &[10_000]).unwrap(), vec![1_000]);
+    }
+
+    #[test]
+    fn split_fee_dust_goes_to_last() {
+        let shares = market().split_fee(101, &[3_333, 3_333, 3_334]).unwrap();
+        assert_eq!(shares, vec![33, 33, 35]);
+        assert_eq!(shares.iter().sum::<u64>(), 101);
+    }
+
+    #[test]
+    fn split_fee_rejects_invalid_bps() {
+        let market = market();
+        assert_eq!(market.split_fee(1_000, &[5_000, 4_999]).unwrap_err(), error(errors::ErrorCode::InvalidFeeSplit));
+        assert_eq!(market.split_fee(1_000, &[]).unwrap_err(), error(errors::ErrorCode::InvalidFeeSplit));
+    }
+
+    #[test]
+    fn validate_exercise_source_checks_mint_and_balance() {
+        let market = market();
+        // 2 contracts pay 100,000,000 of quote plus a 25,000 per contract exercise fee
+        assert_eq!(market.validate_exercise_source(&market.quote_asset_mint, 100_050_000, 2), Ok(()));
+        assert_eq!(
+            market.validate_exercise_source(&market.quote_asset_mint, 100_049_999, 2).unwrap_err(),
+            error(errors::ErrorCode::NotEnoughQuoteAssetsInSrc)
+        );
+        assert_eq!(
+            market.validate_exercise_source(&market.underlying_asset_mint, 100_050_000, 2).unwrap_err(),
+            error(errors::ErrorCode::QuoteSrcMintDoesNotMatchQuoteAsset)
+        );
+    }
+
+    #[test]
+    fn option_tokens_to_burn_matches_minted() {
+        let market = market();
+        for decimals in [0u8, 2, 6, 9].iter() {
+            assert_eq!(
+                market.option_tokens_to_burn(5, *decimals).unwrap(),
+                market.option_tokens_for_contracts(5, *decimals).unwrap()
+            );
+        }
+        assert_eq!(market.option_tokens_to_burn(5, 2).unwrap(), 500);
+    }
+
+    #[test]
+    fn quote_is_in_allowlist() {
+        let market = market();
+        assert!(market.quote_is_in(&[key(9), market.quote_asset_mint]));
+        assert!(!market.quote_is_in(&[key(9), market.underlying_asset_mint]));
+        assert!(!market.quote_is_in(&[]));
+    }
+
+    #[test]
+    fn exercise_net_with_and_without_fee() {
+        let mut market = market();
+        assert_eq!(market.exercise_net(2).unwrap(), (2_000_000, 100_050_000));
+
+        // The exercise fee rounds down to 0 for small quote amounts
+        market.quote_amount_per_contract = 1_000;
+        assert_eq!(market.exercise_net(2).unwrap(), (2_000_000, 2_000));
+    }
+
+    #[test]
+    fn serialize_stays_within_len() {
+        const SENTINEL: u8 = 0xa5;
+        let mut buffer = [SENTINEL; OptionMarket::LEN + 16];
+        let mut cursor = &mut buffer[..];
+        market().serialize(&mut cursor).unwrap();
+        assert_eq!(cursor.len(), 16);
+
+        assert!(buffer[OptionMarket::LEN..].iter().all(|byte| *byte == SENTINEL));
+        assert_eq!(&buffer[..OptionMarket::LEN], &market().try_to_vec().unwrap()[..]);
+    }
+
+    #[test]
+    fn exercise_flow_for_both_types() {
+        let market = market();
+        let expected = ExerciseFlow {
+            debited_pool: market.underlying_asset_pool,
+            credited_pool: market.quote_asset_pool,
+            holder_delivers_mint: market.quote_asset_mint,
+            holder_receives_mint: market.underlying_asset_mint,
+        };
+        assert_eq!(market.exercise_flow(MarketType::Call), expected);
+        assert_eq!(market.exercise_flow(MarketType::Put), expected);
+    }
+
+    #[test]
+    fn settlement_schedule_sweeps_dust_into_last_burn() {
+        let schedule = market().settlement_schedule(&[1, 1, 1], 10, 10, 3).unwrap();
+        assert_eq!(schedule, vec![(3, 3), (3, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn settlement_schedule_does_not_drift() {
+        let (pool, supply) = (10u64, 4u64);
+        let schedule = market().settlement_schedule(&[1, 1, 1, 1], pool, pool, supply).unwrap();
+        assert_eq!(schedule, vec![(2, 2), (2, 2), (2, 2), (4, 4)]);
+        let total: u64 = schedule.iter().map(|(underlying, _)| underlying).sum();
+        assert_eq!(total, pool);
+
+        // Pricing each burn against the pool left by the previous one pays out a
+        // different, order dependent split
+        let (mut naive_pool, mut naive_supply) = (pool, supply);
+        let mut naive = Vec::new();
+        for burn in [1u64, 1, 1, 1].iter() {
+            let payout = naive_pool * burn / naive_supply;
+            naive_pool -= payout;
+            naive_supply -= burn;
+            naive.push(payout);
+        }
+        assert_eq!(naive, vec![2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn settlement_schedule_rejects_overburn() {
+        assert_eq!(
+            market().settlement_schedule(&[2, 2], 10, 10, 3).unwrap_err(),
+            error(errors::ErrorCode::BurnExceedsWriterSupply)
+        );
+    }
+
+    #[test]
+    fn key_tuple_collides_for_identical_economics() {
+        use std::collections::HashSet;
+
+        let mut copy = market();
+        copy.option_mint = key(11);
+        copy.writer_token_mint = key(12);
+        copy.underlying_asset_pool = key(15);
+        copy.quote_asset_pool = key(16);
+        copy.expired = true;
+        let mut other = market();
+        other.quote_amount_per_contract += 1;
+
+        let mut keys = HashSet::new();
+        assert!(keys.insert(market().key_tuple()));
+        assert!(!keys.insert(copy.key_tuple()));
+        assert!(keys.insert(other.key_tuple()));
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn validate_parameters_rejects_negative_expiration() {
+        let mut market = market();
+        market.expiration_unix_timestamp = -1;
+        assert_eq!(market.validate_parameters().unwrap_err(), error(errors::ErrorCode::InvalidExpiration));
+        assert!(market.is_expired(0));
+        assert!(!market.is_expired(-2));
+    }
+
+    #[test]
+    fn validate_parameters_allows_zero_expiration() {
+        let mut market = market();
+        market.expiration_unix_timestamp = 0;
+        assert_eq!(market.validate_parameters(), Ok(()));
+        assert!(!market.is_expired(0));
+        assert!(market.is_expired(1));
+    }
+
+    #[test]
+    fn required_topup_by_collateralization() {
+        let market = market();
+        assert_eq!(market.required_topup(3_000_001, 3), 0);
+        assert_eq!(market.required_topup(3_000_000, 3), 0);
+        assert_eq!(market.required_topup(2_500_000, 3), 500_000);
+        assert_eq!(market.required_topup(0, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn valid_window_per_action() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        assert_eq!(market.valid_window(MarketAction::Mint), (None, Some(expiration)));
+        assert_eq!(market.valid_window(MarketAction::Exercise), (None, Some(expiration)));
+        assert_eq!(market.valid_window(MarketAction::ClosePostExpiration), (Some(expiration + 1), None));
+        assert_eq!(market.valid_window(MarketAction::ClosePosition), (None, None));
+        assert_eq!(market.valid_window(MarketAction::BurnWriterForQuote), (None, None));
+    }
+
+    #[test]
+    fn net_supply_change_nets_to_zero() {
+        let market = market();
+        assert_eq!(market.net_supply_change(7, 7, 0).unwrap(), 0);
+        assert_eq!(market.net_supply_change(7, 7, 6).unwrap(), 0);
+        assert_eq!(market.net_supply_change(7, 2, 0).unwrap(), 5);
+        assert_eq!(market.net_supply_change(0, 2, 2).unwrap(), -200);
+    }
+
+    #[test]
+    fn weekly_bucket_boundaries() {
+        let mut market = market();
+        let epoch_start = 1_000_000;
+        for (expiration, bucket) in [
+            (epoch_start, 0),
+            (epoch_start + SECONDS_PER_WEEK - 1, 0),
+            (epoch_start + SECONDS_PER_WEEK, 1),
+            (epoch_start - 1, -1),
+            (epoch_start - SECONDS_PER_WEEK, -1),
+            (epoch_start - SECONDS_PER_WEEK - 1, -2),
+        ].iter() {
+            market.expiration_unix_timestamp = *expiration;
+            assert_eq!(market.weekly_bucket(epoch_start), *bucket);
+        }
+    }
+
+    #[test]
+    fn mint_fee_display_across_decimals() {
+        let market = market();
+        // The mint fee is 500 base units per contract
+        assert_eq!(market.mint_fee_display(2, 3).unwrap(), (1, 0));
+        assert_eq!(market.mint_fee_display(3, 3).unwrap(), (1, 500));
+        assert_eq!(market.mint_fee_display(3, 6).unwrap(), (0, 1_500));
+        assert_eq!(market.mint_fee_display(3, 0).unwrap(), (1_500, 0));
+    }
+
+    #[test]
+    fn validate_quote_pool_for_settlement_balance() {
+        let market = market();
+        assert_eq!(market.validate_quote_pool_for_settlement(100_000_000, 2), Ok(()));
+        assert_eq!(market.validate_quote_pool_for_settlement(100_000_001, 2), Ok(()));
+        assert_eq!(
+            market.validate_quote_pool_for_settlement(99_999_999, 2).unwrap_err(),
+            error(errors::ErrorCode::NotEnoughQuoteAssetsInPool)
+        );
+    }
+
+    #[test]
+    fn validate_pool_mints_matches_assets() {
+        let market = market();
+        let (underlying, quote) = (market.underlying_asset_mint, market.quote_asset_mint);
+        assert_eq!(market.validate_pool_mints(&underlying, &quote), Ok(()));
+        assert_eq!(
+            market.validate_pool_mints(&quote, &quote).unwrap_err(),
+            error(errors::ErrorCode::UnderlyingPoolMintDoesNotMatchUnderlyingAsset)
+        );
+        assert_eq!(
+            market.validate_pool_mints(&underlying, &underlying).unwrap_err(),
+            error(errors::ErrorCode::QuotePoolMintDoesNotMatchQuoteAsset)
+        );
+    }
+
+    #[test]
+    fn max_exercisable_is_bound_by_pool_and_holder() {
+        let market = market();
+        // The pool only backs 2 whole contracts
+        assert_eq!(market.max_exercisable(5, 2_999_999), 2);
+        // The holder only has 3 contracts
+        assert_eq!(market.max_exercisable(3, 10_000_000), 3);
+        assert_eq!(market.max_exercisable(3, 999_999), 0);
+    }
+
+    #[cfg(feature = "pricing")]
+    #[test]
+    fn years_to_expiry_in_julian_years() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        let thirty_days = 30 * 24 * 60 * 60;
+        assert!((market.years_to_expiry(expiration - thirty_days) - 30.0 / 365.25).abs() < 1e-12);
+        assert!((market.years_to_expiry(expiration - 31_557_600) - 1.0).abs() < 1e-12);
+        assert!(market.years_to_expiry(expiration).abs() < f64::EPSILON);
+        assert!(market.years_to_expiry(expiration + 1).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn validate_writer_mint_matches_market() {
+        let market = market();
+        assert_eq!(market.validate_writer_mint(&market.writer_token_mint), Ok(()));
+        assert_eq!(
+            market.validate_writer_mint(&market.option_mint).unwrap_err(),
+            error(errors::ErrorCode::WriterTokenMintDoesNotMatchMarket)
+        );
+    }
+
+    #[test]
+    fn pool_health_sign() {
+        let market = market();
+        assert_eq!(market.pool_health(3_500_000, 3), 500_000);
+        assert_eq!(market.pool_health(3_000_000, 3), 0);
+        assert_eq!(market.pool_health(2_000_000, 3), -1_000_000);
+    }
+
+    #[test]
+    fn pool_health_saturates() {
+        let mut market = market();
+        market.underlying_amount_per_contract = u64::MAX;
+        assert_eq!(market.pool_health(0, u64::MAX), i128::MIN);
+        assert_eq!(market.pool_health(u64::MAX, 1), 0);
+    }
+
+    #[test]
+    fn find_duplicates_reports_pairs() {
+        let mut other = market();
+        other.expiration_unix_timestamp += 1;
+        let mut copy = market();
+        copy.option_mint = key(11);
+
+        assert_eq!(find_duplicates(&[market(), other.clone(), copy]), vec![(0, 2)]);
+        assert!(find_duplicates(&[market(), other]).is_empty());
+        assert!(find_duplicates(&[]).is_empty());
+    }
+
+    #[test]
+    fn writer_return_expired_worthless_is_profit() {
+        let market = market();
+        let intrinsic = market.intrinsic_value(40_000_000, MarketType::Call);
+        assert_eq!(market.writer_return(3, 6_000_000, MarketType::Call, intrinsic).unwrap(), 6_000_000);
+        assert_eq!(market.writer_return(3, 6_000_000, MarketType::Put, 0).unwrap(), 6_000_000);
+    }
+
+    #[test]
+    fn writer_return_in_the_money_is_loss() {
+        let market = market();
+        let intrinsic = market.intrinsic_value(60_000_000, MarketType::Call);
+        assert_eq!(market.writer_return(3, 6_000_000, MarketType::Call, intrinsic).unwrap(), -24_000_000);
+        // A put's payout is capped at its strike
+        assert_eq!(market.writer_return(2, 100_000, MarketType::Put, 2_000_000).unwrap(), -1_900_000);
+        assert_eq!(
+            market.writer_return(2, 0, MarketType::Call, u64::MAX).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+    }
+
+    #[test]
+    fn require_clock_tolerates_skew() {
+        let market = market();
+        assert_eq!(market.require_clock(1_000, 1_000, 0), Ok(()));
+        assert_eq!(market.require_clock(1_030, 1_000, 30), Ok(()));
+        assert_eq!(market.require_clock(970, 1_000, 30), Ok(()));
+        assert_eq!(market.require_clock(1_031, 1_000, 30).unwrap_err(), error(errors::ErrorCode::ClockSkew));
+        assert_eq!(market.require_clock(969, 1_000, 30).unwrap_err(), error(errors::ErrorCode::ClockSkew));
+        assert_eq!(market.require_clock(i64::MIN, i64::MAX, i64::MAX).unwrap_err(), error(errors::ErrorCode::ClockSkew));
+    }
+
+    #[test]
+    fn quote_share_with_dust_last_burn_sweeps() {
+        let market = market();
+        assert_eq!(market.quote_share_with_dust(1, 10, 3, false).unwrap(), 3);
+        assert_eq!(market.quote_share_with_dust(1, 10, 3, true).unwrap(), 4);
+        // No dust when the pool splits evenly
+        assert_eq!(market.quote_share_with_dust(1, 9, 3, true).unwrap(), 3);
+        // The final burn of the whole supply takes the whole pool
+        assert_eq!(market.quote_share_with_dust(3, 10, 3, true).unwrap(), 10);
+    }
+
+    #[test]
+    fn quote_share_with_dust_last_burn_of_many_contracts() {
+        let market = market();
+        let (pool, supply) = (10u64, 4u64);
+        let first = market.quote_share_with_dust(2, pool, supply, false).unwrap();
+        let last = market.quote_share_with_dust(2, pool, supply, true).unwrap();
+        assert_eq!((first, last), (5, 5));
+        assert_eq!(first + last, pool);
+        assert_eq!(market.settlement_schedule(&[2, 2], pool, pool, supply).unwrap(), vec![(5, 5), (5, 5)]);
+
+        // Uneven splits still sum to the pool, whichever burn comes last
+        let first = market.quote_share_with_dust(2, pool, 3, false).unwrap();
+        let last = market.quote_share_with_dust(1, pool, 3, true).unwrap();
+        assert_eq!((first, last), (6, 4));
+        let first = market.quote_share_with_dust(1, pool, 3, false).unwrap();
+        let last = market.quote_share_with_dust(2, pool, 3, true).unwrap();
+        assert_eq!((first, last), (3, 7));
+    }
+
+    #[test]
+    fn quote_share_with_dust_rejects_bad_supply() {
+        let market = market();
+        assert_eq!(market.quote_share_with_dust(0, 10, 0, true).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+        assert_eq!(
+            market.quote_share_with_dust(4, 10, 3, true).unwrap_err(),
+            error(errors::ErrorCode::BurnExceedsWriterSupply)
+        );
+    }
+
+    #[test]
+    fn max_contracts_with_fee_accounts_for_fee() {
+        let mut market = market();
+        // Dividing by the collateral alone would allow 10 contracts, but with the
+        // 500 base unit fee 10 contracts need 10,005,000
+        assert_eq!(10_000_000 / market.underlying_amount_per_contract, 10);
+        assert_eq!(market.max_contracts_with_fee(10_000_000), 9);
+        assert_eq!(market.max_contracts_with_fee(10_005_000), 10);
+        assert_eq!(market.validate_write_source(&market.underlying_asset_mint, 10_000_000, 9), Ok(()));
+
+        // Without a fee the naive estimate is right
+        market.underlying_amount_per_contract = 100;
+        assert_eq!(market.max_contracts_with_fee(1_000), 10);
+    }
+
+    struct LoadFixture {
+        market_key: Pubkey,
+        market_owner: Pubkey,
+        market_data: Vec<u8>,
+        underlying_key: Pubkey,
+        underlying_owner: Pubkey,
+        underlying_data: Vec<u8>,
+        quote_key: Pubkey,
+        quote_owner: Pubkey,
+        quote_data: Vec<u8>,
+    }
+
+    fn token_account_data(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+        let account = SPLTokenAccount {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..SPLTokenAccount::default()
+        };
+        let mut data = vec![0u8; SPLTokenAccount::LEN];
+        SPLTokenAccount::pack(account, &mut data).unwrap();
+        data
+    }
+
+    /// Run `load_with_pools` against a valid market and pools after applying `tamper`
+    fn load(tamper: impl FnOnce(&mut LoadFixture)) -> Result<(Pubkey, u64, u64), ProgramError> {
+        let market = market();
+        let market_key = key(20);
+        let mut market_data = Vec::new();
+        market.try_serialize(&mut market_data).unwrap();
+        let mut fixture = LoadFixture {
+            market_key,
+            market_owner: crate::ID,
+            market_data,
+            underlying_key: market.underlying_asset_pool,
+            underlying_owner: spl_token::ID,
+            underlying_data: token_account_data(market.underlying_asset_mint, market_key, 3_000_000),
+            quote_key: market.quote_asset_pool,
+            quote_owner: spl_token::ID,
+            quote_data: token_account_data(market.quote_asset_mint, market_key, 100_000_000),
+        };
+        tamper(&mut fixture);
+
+        let mut lamports = [0u64; 3];
+        let [market_lamports, underlying_lamports, quote_lamports] = &mut lamports;
+        let market_info = AccountInfo::new(
+            &fixture.market_key, false, true, market_lamports, &mut fixture.market_data, &fixture.market_owner, false, 0
+        );
+        let underlying_info = AccountInfo::new(
+            &fixture.underlying_key, false, true, underlying_lamports, &mut fixture.underlying_data, &fixture.underlying_owner, false, 0
+        );
+        let quote_info = AccountInfo::new(
+            &fixture.quote_key, false, true, quote_lamports, &mut fixture.quote_data, &fixture.quote_owner, false, 0
+        );
+        OptionMarket::load_with_pools(&market_info, &underlying_info, &quote_info, &crate::ID)
+            .map(|(market, underlying, quote)| (market.option_mint, underlying, quote))
+    }
+
+    #[test]
+    fn load_with_pools_returns_balances() {
+        assert_eq!(load(|_| {}), Ok((market().option_mint, 3_000_000, 100_000_000)));
+    }
+
+    #[test]
+    fn load_with_pools_rejects_tampered_accounts() {
+        assert_eq!(load(|f| f.market_owner = spl_token::ID), Err(ProgramError::IncorrectProgramId));
+        assert_eq!(
+            load(|f| f.underlying_key = key(9)),
+            Err(error(errors::ErrorCode::UnderlyingPoolAccountDoesNotMatchMarket))
+        );
+        assert_eq!(
+            load(|f| f.quote_key = key(9)),
+            Err(error(errors::ErrorCode::QuotePoolAccountDoesNotMatchMarket))
+        );
+        assert_eq!(
+            load(|f| f.quote_owner = crate::ID),
+            Err(error(errors::ErrorCode::ExpectedSPLTokenProgramId))
+        );
+        assert_eq!(
+            load(|f| f.underlying_data = token_account_data(market().quote_asset_mint, f.market_key, 1)),
+            Err(error(errors::ErrorCode::UnderlyingPoolMintDoesNotMatchUnderlyingAsset))
+        );
+        assert_eq!(
+            load(|f| f.underlying_data = token_account_data(market().underlying_asset_mint, key(9), 1)),
+            Err(error(errors::ErrorCode::OptionMarketMustOwnUnderlyingAssetPool))
+        );
+        assert_eq!(
+            load(|f| f.quote_data = token_account_data(market().quote_asset_mint, key(9), 1)),
+            Err(error(errors::ErrorCode::OptionMarketMustOwnQuoteAssetPool))
+        );
+    }
+
+    #[test]
+    fn recoverable_rent_with_and_without_pools() {
+        let rent = Rent::default();
+        let market_rent = rent.minimum_balance(OptionMarket::account_size());
+        assert_eq!(OptionMarket::recoverable_rent(&rent, false, SPLTokenAccount::LEN), market_rent);
+        assert_eq!(
+            OptionMarket::recoverable_rent(&rent, true, SPLTokenAccount::LEN),
+            market_rent + 2 * rent.minimum_balance(SPLTokenAccount::LEN)
+        );
+    }
+
+    #[test]
+    fn validate_option_mint_matches_market() {
+        let market = market();
+        assert_eq!(market.validate_option_mint(&market.option_mint), Ok(()));
+        assert_eq!(
+            market.validate_option_mint(&market.writer_token_mint).unwrap_err(),
+            error(errors::ErrorCode::OptionTokenMintDoesNotMatchMarket)
+        );
+    }
+
+    #[cfg(feature = "pricing")]
+    #[test]
+    fn pricing_inputs_for_known_market() {
+        let market = market();
+        let now = market.expiration_unix_timestamp - 31_557_600 / 2;
+
+        let call = market.pricing_inputs(now, 55_000_000.0, MarketType::Call);
+        assert!((call.strike - 50_000_000.0).abs() < f64::EPSILON);
+        assert!((call.time_to_expiry_years - 0.5).abs() < 1e-12);
+        assert!(call.is_call);
+        assert!((call.spot - 55_000_000.0).abs() < f64::EPSILON);
+
+        let put = market.pricing_inputs(now, 900_000.0, MarketType::Put);
+        assert!((put.strike - 1_000_000.0).abs() < f64::EPSILON);
+        assert!(!put.is_call);
+    }
+
+    fn chain_market(underlying_amount_per_contract: u64, quote_amount_per_contract: u64) -> OptionMarket {
+        let mut market = market();
+        market.underlying_amount_per_contract = underlying_amount_per_contract;
+        market.quote_amount_per_contract = quote_amount_per_contract;
+        market
+    }
+
+    fn strike_of(market: Option<&OptionMarket>) -> Option<(u64, u64)> {
+        market.map(|market| (market.underlying_amount_per_contract, market.quote_amount_per_contract))
+    }
+
+    #[test]
+    fn nearest_atm_around_spot() {
+        let chain = [chain_market(100, 4_000), chain_market(100, 4_500), chain_market(100, 5_000), chain_market(100, 6_000)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (48, 1))), Some((100, 5_000)));
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (44, 1))), Some((100, 4_500)));
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (1_000, 1))), Some((100, 6_000)));
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (0, 1))), Some((100, 4_000)));
+    }
+
+    #[test]
+    fn nearest_atm_per_contract_spot() {
+        let chain = [chain_market(100, 4_000), chain_market(100, 4_500), chain_market(100, 5_000), chain_market(100, 6_000)];
+        assert_eq!(strike_of(nearest_atm(&chain, 4_800)), Some((100, 5_000)));
+        assert_eq!(strike_of(nearest_atm(&chain, 4_400)), Some((100, 4_500)));
+        assert_eq!(strike_of(nearest_atm(&chain, 100_000)), Some((100, 6_000)));
+        assert_eq!(strike_of(nearest_atm(&chain, 0)), Some((100, 4_000)));
+        // Ties go to the lower strike
+        assert_eq!(strike_of(nearest_atm(&chain, 5_500)), Some((100, 5_000)));
+        assert!(nearest_atm(&[], 5_000).is_none());
+        assert!(nearest_atm(&[chain_market(0, 5_000)], 5_000).is_none());
+    }
+
+    #[test]
+    fn nearest_atm_compares_strikes_not_contract_amounts() {
+        // Strikes 50, 55 and 60 with different contract sizes
+        let chain = [chain_market(100, 5_000), chain_market(200, 11_000), chain_market(100, 6_000)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (55, 1))), Some((200, 11_000)));
+        // Fractional spots on either side of the 57.5 midpoint
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (229, 4))), Some((200, 11_000)));
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (231, 4))), Some((100, 6_000)));
+    }
+
+    #[test]
+    fn nearest_atm_ties_go_to_lower_strike() {
+        let chain = [chain_market(100, 6_000), chain_market(100, 5_000)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (55, 1))), Some((100, 5_000)));
+        let chain = [chain_market(100, 6_000), chain_market(200, 11_000)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (115, 2))), Some((200, 11_000)));
+        // Equal strikes keep the first market
+        let chain = [chain_market(200, 11_000), chain_market(100, 5_500)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (55, 1))), Some((200, 11_000)));
+    }
+
+    #[test]
+    fn nearest_atm_without_candidates() {
+        assert!(nearest_atm_ratio(&[], (55, 1)).is_none());
+        assert!(nearest_atm_ratio(&[market()], (55, 0)).is_none());
+        assert!(nearest_atm_ratio(&[chain_market(0, 5_000)], (55, 1)).is_none());
+    }
+
+    #[test]
+    fn nearest_atm_compares_past_128_bits() {
+        let chain = [chain_market(1, u64::MAX), chain_market(2, u64::MAX)];
+        assert_eq!(strike_of(nearest_atm_ratio(&chain, (0, u64::MAX))), Some((2, u64::MAX)));
+        assert_eq!(wide_mul(u128::MAX, u64::MAX), (u64::MAX as u128 - 1, u128::MAX - (u64::MAX as u128 - 1)));
+        assert_eq!(wide_mul(u128::MAX, 1), (0, u128::MAX));
+    }
+
+    #[test]
+    fn exercised_fraction_bps_range() {
+        let market = market();
+        assert_eq!(market.exercised_fraction_bps(10, 10), 0);
+        assert_eq!(market.exercised_fraction_bps(5, 10), 5_000);
+        assert_eq!(market.exercised_fraction_bps(0, 10), 10_000);
+        assert_eq!(market.exercised_fraction_bps(2, 3), 3_333);
+        assert_eq!(market.exercised_fraction_bps(0, 0), 0);
+    }
+
+    #[test]
+    fn signer_seeds_rederive_bump() {
+        let (market, address) = derived_market();
+        let seed_buf = market.signer_seeds();
+        let seeds = seed_buf.seeds();
+        let (found, bump_seed) = Pubkey::find_program_address(&seeds[..5], &crate::ID);
+        assert_eq!(bump_seed, market.bump_seed);
+        assert_eq!(seeds[5], &[market.bump_seed]);
+        assert_eq!(found, address);
+        assert_eq!(found, market.market_address(&crate::ID).unwrap());
+        assert_eq!(Pubkey::create_program_address(&seeds, &crate::ID).unwrap(), found);
+    }
+
+    #[test]
+    fn validate_exercise_fee_account_holds_quote() {
+        let market = market();
+        assert_eq!(market.validate_exercise_fee_account(&market.quote_asset_mint), Ok(()));
+        assert_eq!(
+            market.validate_exercise_fee_account(&market.underlying_asset_mint).unwrap_err(),
+            error(errors::ErrorCode::ExerciseFeeTokenMustMatchQuoteAsset)
+        );
+    }
+
+    #[test]
+    fn estimated_exercise_cu_fits_default_budget() {
+        let estimate = market().estimated_exercise_cu();
+        assert_eq!(estimate, 70_000);
+        // Above the base cost and within the default 200,000 compute units per instruction
+        assert!(estimate > EXERCISE_BASE_CU && estimate <= 200_000);
+    }
+
+    #[test]
+    fn to_bytes_round_trip() {
+        let mut market = market();
+        market.expired = true;
+        market.bump_seed = 254;
+        let bytes = market.to_bytes();
+        assert_eq!(&bytes[..], &market.try_to_vec().unwrap()[..]);
+
+        let decoded = OptionMarket::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(decoded.expired);
+        assert_eq!(decoded.bump_seed, 254);
+        assert_eq!(decoded.key_tuple(), market.key_tuple());
+    }
+
+    #[test]
+    fn validate_max_tenor_bounds() {
+        let market = market();
+        let now = market.expiration_unix_timestamp - 1_000;
+        assert_eq!(market.validate_max_tenor(now, 1_001), Ok(()));
+        assert_eq!(market.validate_max_tenor(now, 1_000), Ok(()));
+        assert_eq!(market.validate_max_tenor(now, 999).unwrap_err(), error(errors::ErrorCode::TenorTooLong));
+        assert_eq!(market.validate_max_tenor(now, 0), Ok(()));
+        assert_eq!(market.validate_max_tenor(i64::MIN, 0), Ok(()));
+    }
+
+    #[test]
+    fn early_close_return_releases_collateral() {
+        let market = market();
+        assert_eq!(market.early_close_return(0).unwrap(), 0);
+        assert_eq!(market.early_close_return(4).unwrap(), 4_000_000);
+        assert_eq!(market.early_close_return(u64::MAX).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn validate_early_close_checks() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        assert_eq!(market.validate_early_close(3, 3, expiration), Ok(()));
+        assert_eq!(
+            market.validate_early_close(3, 2, expiration).unwrap_err(),
+            error(errors::ErrorCode::UnequalEarlyCloseBurns)
+        );
+        assert_eq!(
+            market.validate_early_close(3, 3, expiration + 1).unwrap_err(),
+            error(errors::ErrorCode::OptionMarketExpiredCantClosePosition)
+        );
+        // The program itself doesn't restrict when a position can be closed
+        assert_eq!(market.valid_window(MarketAction::ClosePosition), (None, None));
+    }
+
+    #[test]
+    fn is_dust_pool_threshold() {
+        let market = market();
+        assert!(market.is_dust_pool(99, 100));
+        assert!(market.is_dust_pool(100, 100));
+        assert!(!market.is_dust_pool(101, 100));
+        assert!(!market.is_dust_pool(0, 100));
+    }
+
+    #[test]
+    fn fingerprint_is_deterministic() {
+        let market = market();
+        assert_eq!(market.fingerprint(), 4_098_161_672_289_497_541);
+        assert_eq!(market.clone().fingerprint(), market.fingerprint());
+
+        let mut other = market.clone();
+        other.quote_amount_per_contract += 1;
+        assert_eq!(other.fingerprint(), market.fingerprint());
+        other.option_mint = key(11);
+        assert_ne!(other.fingerprint(), market.fingerprint());
+    }
+
+    #[test]
+    fn validate_parameters_rejects_identical_pools() {
+        let mut market = market();
+        assert_eq!(market.validate_parameters(), Ok(()));
+        market.quote_asset_pool = market.underlying_asset_pool;
+        assert_eq!(market.validate_parameters().unwrap_err(), error(errors::ErrorCode::IdenticalPools));
+    }
+
+    #[test]
+    fn writer_total_collateral_sums_positions() {
+        let positions = [(market(), 3), (chain_market(250, 5_000), 4)];
+        assert_eq!(writer_total_collateral(&positions).unwrap(), 3_001_000);
+        assert_eq!(writer_total_collateral(&[]).unwrap(), 0);
+
+        let overflow = [(market(), u64::MAX / 1_000_000), (market(), 1)];
+        assert_eq!(writer_total_collateral(&overflow).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn validate_against_registry_checks_both_assets() {
+        let market = market();
+        let (underlying, quote) = (market.underlying_asset_mint, market.quote_asset_mint);
+        assert_eq!(market.validate_against_registry(&[quote, key(9), underlying]), Ok(()));
+        assert_eq!(
+            market.validate_against_registry(&[quote]).unwrap_err(),
+            error(errors::ErrorCode::UnderlyingAssetNotAllowed)
+        );
+        assert_eq!(
+            market.validate_against_registry(&[underlying]).unwrap_err(),
+            error(errors::ErrorCode::QuoteAssetNotAllowed)
+        );
+        assert_eq!(
+            market.validate_against_registry(&[]).unwrap_err(),
+            error(errors::ErrorCode::UnderlyingAssetNotAllowed)
+        );
+    }
+
+    #[test]
+    fn tokens_needed_for_unwind_across_decimals() {
+        let market = market();
+        assert_eq!(market.tokens_needed_for_unwind(3, 0).unwrap(), (3, 3));
+        assert_eq!(market.tokens_needed_for_unwind(3, 6).unwrap(), (3_000_000, 3_000_000));
+        assert_eq!(market.tokens_needed_for_unwind(u64::MAX, 1).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn moneyness_bps_by_type() {
+        let market = market();
+        assert_eq!(market.moneyness_bps(55_000_000, MarketType::Call).unwrap(), 1_000);
+        assert_eq!(market.moneyness_bps(45_000_000, MarketType::Call).unwrap(), -1_000);
+        assert_eq!(market.moneyness_bps(50_000_000, MarketType::Call).unwrap(), 0);
+        assert_eq!(market.moneyness_bps(900_000, MarketType::Put).unwrap(), 1_000);
+        assert_eq!(market.moneyness_bps(1_100_000, MarketType::Put).unwrap(), -1_000);
+        assert_eq!(market.moneyness_bps(1_000_000, MarketType::Put).unwrap(), 0);
+        // Truncated toward 0
+        assert_eq!(market.moneyness_bps(50_004_999, MarketType::Call).unwrap(), 0);
+        assert_eq!(market.moneyness_bps(49_995_001, MarketType::Call).unwrap(), 0);
+    }
+
+    #[test]
+    fn moneyness_bps_errors() {
+        let market = chain_market(1, 1);
+        assert_eq!(market.moneyness_bps(u64::MAX, MarketType::Call).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+        let zero_strike = chain_market(1, 0);
+        assert_eq!(zero_strike.moneyness_bps(1, MarketType::Call).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+    }
+
+    #[test]
+    fn effective_exercise_price_with_and_without_fee() {
+        let mut market = market();
+        // 50,025,000 quote per 1,000,000 underlying with the exercise fee
+        assert_eq!(market.effective_exercise_price(2).unwrap(), (2_001, 40));
+
+        market.quote_amount_per_contract = 1_000;
+        assert_eq!(market.effective_exercise_price(2).unwrap(), (1, 1_000));
+        assert_eq!(market.effective_exercise_price(0).unwrap_err(), error(errors::ErrorCode::SizeCantBeLessThanEqZero));
+    }
+
+    #[test]
+    fn validate_min_tenor_bounds() {
+        let market = market();
+        let now = market.expiration_unix_timestamp - 1_000;
+        assert_eq!(market.validate_min_tenor(now, 999), Ok(()));
+        assert_eq!(market.validate_min_tenor(now, 1_000), Ok(()));
+        assert_eq!(market.validate_min_tenor(now, 1_001).unwrap_err(), error(errors::ErrorCode::TenorTooShort));
+    }
+
+    #[test]
+    fn simulate_quote_pool_accumulates() {
+        let market = market();
+        assert_eq!(market.simulate_quote_pool(&[]).unwrap(), 0);
+        assert_eq!(market.simulate_quote_pool(&[1, 0, 3]).unwrap(), 200_000_000);
+        assert_eq!(market.simulate_quote_pool(&[1, 3]).unwrap(), market.expected_quote_pool(4).unwrap());
+
+        let max_contracts = u64::MAX / market.quote_amount_per_contract;
+        assert_eq!(
+            market.simulate_quote_pool(&[max_contracts, 1]).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+    }
+
+    #[test]
+    fn break_even_ratio_call_and_put() {
+        let market = market();
+        // (50,000,000 + 2,500,000) / 1,000,000 reduces to 105 / 2
+        assert_eq!(market.break_even_ratio(2_500_000, MarketType::Call).unwrap(), (105, 2));
+        assert_eq!(market.break_even_ratio(3_000_000, MarketType::Call).unwrap(), (53, 1));
+        // (1,000,000 - 100,000) / 50,000,000
+        assert_eq!(market.break_even_ratio(100_000, MarketType::Put).unwrap(), (9, 500));
+        assert_eq!(chain_market(7, 11).break_even_ratio(1, MarketType::Put).unwrap(), (6, 11));
+        assert_eq!(market.break_even_ratio(2_000_000, MarketType::Put).unwrap(), (0, 1));
+    }
+
+    #[test]
+    fn break_even_ratio_errors() {
+        assert_eq!(
+            market().break_even_ratio(u64::MAX, MarketType::Call).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+        assert_eq!(
+            chain_market(0, 5).break_even_ratio(1, MarketType::Call).unwrap_err(),
+            error(errors::ErrorCode::DivideByZero)
+        );
+    }
+
+    #[test]
+    fn require_writable_account() {
+        let key = key(20);
+        let (mut lamports, mut data) = (0u64, [0u8; 0]);
+        let mut account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        assert_eq!(OptionMarket::require_writable(&account), Ok(()));
+        account.is_writable = false;
+        assert_eq!(OptionMarket::require_writable(&account), Err(ProgramError::InvalidArgument));
+    }
+
+    #[test]
+    fn fee_refund_for_uncompleted_contracts() {
+        let market = market();
+        assert_eq!(market.fee_refund(5_000, 10, 10).unwrap(), 0);
+        assert_eq!(market.fee_refund(5_000, 4, 10).unwrap(), 3_000);
+        assert_eq!(market.fee_refund(1_000, 1, 3).unwrap(), 666);
+        assert_eq!(market.fee_refund(5_000, 0, 10).unwrap(), 5_000);
+        assert_eq!(market.fee_refund(5_000, 0, 0).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+    }
+
+    #[cfg(feature = "pricing")]
+    #[test]
+    fn hedge_ratio_scales_delta() {
+        let market = market();
+        assert!((market.hedge_ratio(0.5) - 500_000.0).abs() < f64::EPSILON);
+        assert!((market.hedge_ratio(-0.25) + 250_000.0).abs() < f64::EPSILON);
+        assert!(market.hedge_ratio(0.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn expires_before_deadline() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        assert!(!market.expires_before(expiration - 1));
+        assert!(market.expires_before(expiration));
+        assert!(market.expires_before(expiration + 1));
+        assert_eq!(
+            market.validate_expires_before(expiration - 1).unwrap_err(),
+            error(errors::ErrorCode::ExpiryAfterDeadline)
+        );
+        assert_eq!(market.validate_expires_before(expiration), Ok(()));
+        assert_eq!(market.validate_expires_before(expiration + 1), Ok(()));
+    }
+
+    #[test]
+    fn writer_quote_for_assigned_contracts() {
+        let market = market();
+        assert_eq!(market.writer_quote_per_assigned_contract(), 50_000_000);
+        assert_eq!(market.writer_quote_for_assigned(3).unwrap(), 150_000_000);
+        assert_eq!(market.writer_quote_for_assigned(3).unwrap(), market.expected_quote_pool(3).unwrap());
+        assert_eq!(market.writer_quote_for_assigned(u64::MAX).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    #[test]
+    fn account_size_matches_components() {
+        // Four mints, three amounts, four token accounts, the expired flag and the bump seed
+        let fields = 4 * 32 + 3 * 8 + 4 * 32 + 1 + 1;
+        assert_eq!(OptionMarket::LEN, fields);
+        assert_eq!(OptionMarket::account_size(), 8 + fields);
+        assert_eq!(market().try_to_vec().unwrap().len(), OptionMarket::LEN);
+
+        let mut data = Vec::new();
+        market().try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), OptionMarket::account_size());
+        let rent = Rent::default();
+        assert_eq!(OptionMarket::rent_exempt_lamports(&rent), rent.minimum_balance(data.len()));
+    }
+
+    #[test]
+    fn max_backed_option_tokens_across_decimals() {
+        let market = market();
+        assert_eq!(market.max_backed_option_tokens(3_000_000, 0).unwrap(), 3);
+        assert_eq!(market.max_backed_option_tokens(3_000_000, 6).unwrap(), 3_000_000);
+        // Partial contracts back nothing
+        assert_eq!(market.max_backed_option_tokens(3_999_999, 0).unwrap(), 3);
+        assert_eq!(market.max_backed_option_tokens(999_999, 2).unwrap(), 0);
+        assert_eq!(
+            chain_market(0, 1).max_backed_option_tokens(1, 0).unwrap_err(),
+            error(errors::ErrorCode::DivideByZero)
+        );
+    }
+
+    #[test]
+    fn weighted_avg_strike_of_positions() {
+        let positions = [(chain_market(100, 5_000), 1), (chain_market(100, 6_000), 3)];
+        assert_eq!(weighted_avg_strike(&positions).unwrap(), (115, 2));
+        // Weighted by underlying notional when contract sizes differ
+        let positions = [(chain_market(100, 5_000), 1), (chain_market(300, 18_000), 1)];
+        assert_eq!(weighted_avg_strike(&positions).unwrap(), (115, 2));
+    }
+
+    #[test]
+    fn weighted_avg_strike_errors() {
+        assert_eq!(weighted_avg_strike(&[]).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+        assert_eq!(weighted_avg_strike(&[(market(), 0)]).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+        assert_eq!(
+            weighted_avg_strike(&[(market(), u64::MAX)]).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+    }
+
+    #[test]
+    fn validate_parameters_rejects_fee_account_pools() {
+        let base = market();
+        let pools = [base.underlying_asset_pool, base.quote_asset_pool];
+        for pool in pools.iter() {
+            let mut market = base.clone();
+            market.mint_fee_account = *pool;
+            assert_eq!(market.validate_parameters().unwrap_err(), error(errors::ErrorCode::FeeAccountIsPool));
+
+            let mut market = base.clone();
+            market.exercise_fee_account = *pool;
+            assert_eq!(market.validate_parameters().unwrap_err(), error(errors::ErrorCode::FeeAccountIsPool));
+        }
+    }
+
+    #[test]
+    fn validate_option_decimals_bound() {
+        let market = market();
+        assert_eq!(market.validate_option_decimals(0, 6), Ok(()));
+        assert_eq!(market.validate_option_decimals(6, 6), Ok(()));
+        assert_eq!(market.validate_option_decimals(7, 6).unwrap_err(), error(errors::ErrorCode::UnsupportedDecimals));
+    }
+
+    #[test]
+    fn writer_underlying_claim_shares() {
+        let market = market();
+        assert_eq!(market.writer_underlying_claim(10, 7_000_001, 10).unwrap(), 7_000_001);
+        assert_eq!(market.writer_underlying_claim(3, 7_000_001, 10).unwrap(), 2_100_000);
+        assert_eq!(market.writer_underlying_claim(0, 7_000_001, 10).unwrap(), 0);
+        assert_eq!(market.writer_underlying_claim(0, 7_000_001, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn writer_underlying_claim_rejects_balance_above_supply() {
+        let market = market();
+        assert_eq!(
+            market.writer_underlying_claim(11, 7_000_001, 10).unwrap_err(),
+            error(errors::ErrorCode::BurnExceedsWriterSupply)
+        );
+        assert_eq!(
+            market.writer_underlying_claim(1, 7_000_001, 0).unwrap_err(),
+            error(errors::ErrorCode::BurnExceedsWriterSupply)
+        );
+    }
+
+    #[test]
+    fn gross_underlying_with_and_without_fee() {
+        let market = market();
+        assert_eq!(market.gross_underlying_for_contracts(3).unwrap(), 3_001_500);
+        // The fee rounds down to 0 for small contracts
+        assert_eq!(chain_market(100, 5_000).gross_underlying_for_contracts(3).unwrap(), 300);
+        assert_eq!(
+            market.gross_underlying_for_contracts(u64::MAX / 1_000_000).unwrap_err(),
+            error(errors::ErrorCode::NumberOverflow)
+        );
+    }
+
+    #[test]
+    fn phase_boundaries() {
+        let market = market();
+        let expiration = market.expiration_unix_timestamp;
+        assert_eq!(market.phase(expiration - 1), MarketPhase::Active);
+        assert_eq!(market.phase(expiration), MarketPhase::Active);
+        assert_eq!(market.phase(expiration + 1), MarketPhase::Expired);
+    }
+
+    #[test]
+    fn strike_sanity_bounds() {
+        // The strike is 50 quote per unit of underlying
+        let market = market();
+        assert_eq!(market.strike_sanity((50, 1), (50, 1)), Ok(()));
+        assert_eq!(market.strike_sanity((99, 2), (101, 2)), Ok(()));
+        assert_eq!(market.strike_sanity((101, 2), (60, 1)).unwrap_err(), error(errors::ErrorCode::StrikeOutOfRange));
+        assert_eq!(market.strike_sanity((1, 1), (99, 2)).unwrap_err(), error(errors::ErrorCode::StrikeOutOfRange));
+        assert_eq!(market.strike_sanity((1, 0), (60, 1)).unwrap_err(), error(errors::ErrorCode::DivideByZero));
+    }
+
+    #[test]
+    fn contracts_and_remainder_split() {
+        let market = market();
+        assert_eq!(market.contracts_and_remainder(3_000_000, 6).unwrap(), (3, 0));
+        assert_eq!(market.contracts_and_remainder(3_000_001, 6).unwrap(), (3, 1));
+        assert_eq!(market.contracts_and_remainder(999_999, 6).unwrap(), (0, 999_999));
+        assert_eq!(market.contracts_and_remainder(7, 0).unwrap(), (7, 0));
+        assert_eq!(market.contracts_and_remainder(7, 20).unwrap_err(), error(errors::ErrorCode::NumberOverflow));
+    }
+
+    /// An account passed to `validate_init_accounts`
+    struct InitAccount {
+        key: Pubkey,
+        is_signer: bool,
+        is_writable: bool,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+    }
+
+    /// Run `validate_init_accounts` over a valid set of accounts after `tamper`
+    fn validate_init(tamper: impl FnOnce(&mut Vec<InitAccount>)) -> Result<(), ProgramError> {
+        let option_market = key(100 + INIT_OPTION_MARKET_INDEX as u8);
+        let mut accounts: Vec<InitAccount> = OptionMarket::init_account_layout()
+            .iter()
+            .enumerate()
+            .map(|(i, role)| {
+                let key = match role.check {
+                    AccountCheck::FeeOwner => fees::fee_owner_key::ID,
+                    AccountCheck::TokenProgram => spl_token::ID,
+                    AccountCheck::AssociatedTokenProgram => anchor_spl::associated_token::ID,
+                    AccountCheck::SystemProgram => system_program::ID,
+                    AccountCheck::Rent => sysvar::rent::ID,
+                    AccountCheck::Clock => sysvar::clock::ID,
+                    AccountCheck::MarketAddress(seed) => {
+                        Pubkey::find_program_address(&[option_market.as_ref(), seed], &crate::ID).0
+                    },
+                    AccountCheck::Any | AccountCheck::Uninitialized => key(100 + i as u8),
+                };
+                InitAccount {
+                    key,
+                    is_signer: role.is_signer,
+                    is_writable: role.is_writable,
+                    owner: system_program::ID,
+                    lamports: 0,
+                    data: vec![],
+                }
+            })
+            .collect();
+        tamper(&mut accounts);
+        let infos: Vec<AccountInfo> = accounts
+            .iter_mut()
+            .map(|a| AccountInfo::new(&a.key, a.is_signer, a.is_writable, &mut a.lamports, &mut a.data, &a.owner, false, 0))
+            .collect();
+        OptionMarket::validate_init_accounts(&infos, &crate::ID)
+    }
+
+    #[test]
+    fn validate_init_accounts_valid_set() {
+        assert_eq!(validate_init(|_| {}), Ok(()));
+        // a pre-funded account is still created by `init`
+        assert_eq!(validate_init(|a| a[7].lamports = 1_000_000), Ok(()));
+    }
+
+    #[test]
+    fn validate_init_accounts_wrong_count() {
+        assert_eq!(validate_init(|a| { a.pop(); }), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(validate_init(|a| a.clear()), Err(ProgramError::NotEnoughAccountKeys));
+    }
+
+    #[test]
+    fn validate_init_accounts_flags() {
+        assert_eq!(validate_init(|a| a[0].is_signer = false), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(validate_init(|a| a[6].is_writable = false), Err(ProgramError::InvalidArgument));
+    }
+
+    #[test]
+    fn validate_init_accounts_checks() {
+        // every `init` account must not exist yet, including one the program owns
+        for index in 3..=7 {
+            assert_eq!(validate_init(|a| a[index].owner = crate::ID), Err(ProgramError::IllegalOwner));
+            assert_eq!(validate_init(|a| a[index].owner = key(9)), Err(ProgramError::IllegalOwner));
+            assert_eq!(validate_init(|a| a[index].data = vec![0]), Err(ProgramError::AccountAlreadyInitialized));
+        }
+        // swapped pools aren't at their derived addresses
+        assert_eq!(validate_init(|a| a.swap(5, 6)), Err(ProgramError::InvalidSeeds));
+        assert_eq!(validate_init(|a| a[3].key = key(9)), Err(ProgramError::InvalidSeeds));
+        // the mints and pools are derived from the market passed
+        assert_eq!(validate_init(|a| a[7].key = key(9)), Err(ProgramError::InvalidSeeds));
+        assert_eq!(validate_init(|a| a[8].key = key(9)), Err(error(errors::ErrorCode::FeeOwnerDoesNotMatchProgram)));
+        assert_eq!(validate_init(|a| a[9].key = key(9)), Err(error(errors::ErrorCode::ExpectedSPLTokenProgramId)));
+        assert_eq!(validate_init(|a| a[10].key = key(9)), Err(ProgramError::IncorrectProgramId));
+        assert_eq!(validate_init(|a| a[11].key = key(9)), Err(ProgramError::InvalidArgument));
+        assert_eq!(validate_init(|a| a[12].key = key(9)), Err(ProgramError::IncorrectProgramId));
+        assert_eq!(validate_init(|a| a[13].key = key(9)), Err(ProgramError::InvalidArgument));
+    }
+
+    #[test]
+    fn risk_bucket_call() {
+        let market = market();
+        let near = market.expiration_unix_timestamp - NEAR_EXPIRY_SECS;
+        let far = near - 1;
+        assert_eq!(market.risk_bucket(near, 55_000_000, MarketType::Call), RiskBucket::NearInTheMoney);
+        assert_eq!(market.risk_bucket(near, 51_000_000, MarketType::Call), RiskBucket::NearAtTheMoney);
+        assert_eq!(market.risk_bucket(near, 45_000_000, MarketType::Call), RiskBucket::NearOutOfTheMoney);
+        assert_eq!(market.risk_bucket(far, 55_000_000, MarketType::Call), RiskBucket::FarInTheMoney);
+        assert_eq!(market.risk_bucket(far, 51_000_000, MarketType::Call), RiskBucket::FarAtTheMoney);
+        assert_eq!(market.risk_bucket(far, 45_000_000, MarketType::Call), RiskBucket::FarOutOfTheMoney);
+    }
+
+    #[test]
+    fn risk_bucket_put() {
+        let market = market();
+        let near = market.expiration_unix_timestamp - NEAR_EXPIRY_SECS;
+        let far = near - 1;
+        assert_eq!(market.risk_bucket(near, 900_000, MarketType::Put), RiskBucket::NearInTheMoney);
+        assert_eq!(market.risk_bucket(near, 980_000, MarketType::Put), RiskBucket::NearAtTheMoney);
+        assert_eq!(market.risk_bucket(near, 1_100_000, MarketType::Put), RiskBucket::NearOutOfTheMoney);
+        assert_eq!(market.risk_bucket(far, 900_000, MarketType::Put), RiskBucket::FarInTheMoney);
+        assert_eq!(market.risk_bucket(far, 980_000, MarketType::Put), RiskBucket::FarAtTheMoney);
+        assert_eq!(market.risk_bucket(far, 1_100_000, MarketType::Put), RiskBucket::FarOutOfTheMoney);
+    }
+
+    #[test]
+    fn risk_bucket_edges() {
+        let mut market = market();
+        let far = market.expiration_unix_timestamp - NEAR_EXPIRY_SECS - 1;
+        // +500 bps is still at the money, +510 is not
+        assert_eq!(market.risk_bucket(far, 52_500_000, MarketType::Call), RiskBucket::FarAtTheMoney);
+        assert_eq!(market.risk_bucket(far, 52_550_000, MarketType::Call), RiskBucket::FarInTheMoney);
+        assert_eq!(market.risk_bucket(far, 47_500_000, MarketType::Call), RiskBucket::FarAtTheMoney);
+        assert_eq!(market.risk_bucket(far, 47_450_000, MarketType::Call), RiskBucket::FarOutOfTheMoney);
+        // an expired market is near
+        let expired = market.expiration_unix_timestamp + 1;
+        assert_eq!(market.risk_bucket(expired, 55_000_000, MarketType::Call), RiskBucket::NearInTheMoney);
+        // a zero strike has no moneyness and counts as at the money
+        market.quote_amount_per_contract = 0;
+        assert_eq!(market.risk_bucket(far, 55_000_000, MarketType::Call), RiskBucket::FarAtTheMoney);
+    }
+
+    #[test]
+    fn validate_contract_alignment_whole_contracts() {
+        let market = market();
+        assert_eq!(market.validate_contract_alignment(3_000_000, 6), Ok(3));
+        assert_eq!(market.validate_contract_alignment(0, 6), Ok(0));
+        assert_eq!(market.validate_contract_alignment(7, 0), Ok(7));
+    }
+
+    #[test]
+    fn validate_contract_alignment_rejects_fractions() {
+        let market = market();
+        assert_eq!(market.validate_contract_alignment(3_000_001, 6), Err(error(errors::ErrorCode::UnalignedExercise)));
+        assert_eq!(market.validate_contract_alignment(999_999, 6), Err(error(errors::ErrorCode::UnalignedExercise)));
+        assert_eq!(market.validate_contract_alignment(7, 20), Err(error(errors::ErrorCode::NumberOverflow)));
+    }
+}
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::dex::{Context, MarketMiddleware};
 use solana_program::clock::Clock;
 
-use crate::{errors, OptionMarket};
+use crate::{errors, instructions, OptionMarket};
 
 pub struct Validation {
     pub market_auth_bump: u8,
@@ -17,17 +17,21 @@ impl Validation {
 impl MarketMiddleware for Validation {
     fn instruction(&mut self, data: &mut &[u8]) -> ProgramResult {
         // Strip the Validation discriminator
-        let disc = data[0];
-        *data = &data[1..];
+        let bytes: &[u8] = data;
+        let (disc, rest) = bytes.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        *data = rest;
         // 6 is the Prune instruction, strip and set the marketAuthorityBump
-        if disc == 6 {
-            self.market_auth_bump = data[0];
-            *data = &data[1..];
+        if *disc == 6 {
+            let (bump, rest) = rest.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+            self.market_auth_bump = *bump;
+            *data = rest;
         }
         Ok(())
     }
 
     fn prune(&self, ctx: &mut Context, _limit: u16) -> ProgramResult {
+        // The OptionMarket followed by the prune accounts, the 4th of which is signed for
+        instructions::require_account_count(&ctx.accounts, 5)?;
         // Validate that the OptionMarket has expired
         // deserialize the OptionMarket
         let option_market_account = ctx.accounts[0].clone();
@@ -59,3 +63,49 @@ impl MarketMiddleware for Validation {
 pub mod referral {
     solana_program::declare_id!("6c33US7ErPmLXZog9SyChQUYUrrJY51k4GmzdhrbhNnD");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruction_strips_discriminator_and_bump() {
+        let mut validation = Validation::new();
+        let mut data: &[u8] = &[1, 9];
+        assert_eq!(validation.instruction(&mut data), Ok(()));
+        assert_eq!(data, &[9]);
+        assert_eq!(validation.market_auth_bump, 0);
+
+        let mut data: &[u8] = &[6, 254, 9];
+        assert_eq!(validation.instruction(&mut data), Ok(()));
+        assert_eq!(data, &[9]);
+        assert_eq!(validation.market_auth_bump, 254);
+    }
+
+    #[test]
+    fn instruction_rejects_short_data() {
+        let mut validation = Validation::new();
+        let mut data: &[u8] = &[];
+        assert_eq!(validation.instruction(&mut data), Err(ProgramError::InvalidInstructionData));
+        // Prune without its bump seed
+        let mut data: &[u8] = &[6];
+        assert_eq!(validation.instruction(&mut data), Err(ProgramError::InvalidInstructionData));
+    }
+
+    #[test]
+    fn prune_rejects_too_few_accounts() {
+        let key = Pubkey::new_unique();
+        let mut lamports = [0u64; 4];
+        let mut data = [[0u8; 0]; 4];
+        let accounts: Vec<AccountInfo> = lamports
+            .iter_mut()
+            .zip(data.iter_mut())
+            .map(|(lamports, data)| AccountInfo::new(&key, false, false, lamports, data, &key, false, 0))
+            .collect();
+        let validation = Validation::new();
+        let mut ctx = Context::new(&key, &key, accounts);
+        assert_eq!(validation.prune(&mut ctx, 0), Err(ProgramError::NotEnoughAccountKeys));
+        let mut ctx = Context::new(&key, &key, vec![]);
+        assert_eq!(validation.prune(&mut ctx, 0), Err(ProgramError::NotEnoughAccountKeys));
+    }
+}
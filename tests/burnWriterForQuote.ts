@@ -17,7 +17,9 @@ import {
   initNewTokenMint,
   initOptionMarket,
   initSetup,
+  marketFingerprint,
   wait,
+  waitForEvent,
 } from "../utils/helpers";
 import { OptionMarketV2 } from "../packages/psyoptions-ts/src/types";
 import { mintOptionsTx } from "../packages/psyoptions-ts/src";
@@ -225,16 +227,22 @@ describe("burnWriterForQuote", () => {
           const writerQuoteBefore = await quoteToken.getAccountInfo(
             minterQuoteAccount.publicKey
           );
+          let settlementEvent: any;
           try {
-            await burnWriterForQuote(
+            settlementEvent = await waitForEvent(
               program,
-              minter,
-              size,
-              optionMarket.key,
-              optionMarket.writerTokenMint,
-              minterWriterAcct.publicKey,
-              optionMarket.quoteAssetPool,
-              minterQuoteAccount.publicKey
+              "SettlementEvent",
+              () =>
+                burnWriterForQuote(
+                  program,
+                  minter,
+                  size,
+                  optionMarket.key,
+                  optionMarket.writerTokenMint,
+                  minterWriterAcct.publicKey,
+                  optionMarket.quoteAssetPool,
+                  minterQuoteAccount.publicKey
+                )
             );
           } catch (err) {
             console.error((err as Error).toString());
@@ -256,6 +264,20 @@ describe("burnWriterForQuote", () => {
             writerQuoteDiff.toString(),
             size.mul(quoteAmountPerContract).toString()
           );
+
+          assert.equal(
+            settlementEvent.optionMint.toString(),
+            optionMarket.optionMint.toString()
+          );
+          assert.equal(
+            settlementEvent.fingerprint.toString(),
+            marketFingerprint(optionMarket.optionMint)
+          );
+          assert.equal(settlementEvent.underlyingReclaimed.toString(), "0");
+          assert.equal(
+            settlementEvent.quoteDistributed.toString(),
+            writerQuoteDiff.toString()
+          );
         });
       });
       describe("Quote pool does not match OptionMarket", () => {
@@ -15,7 +15,9 @@ import {
   initNewTokenMint,
   initOptionMarket,
   initSetup,
+  marketFingerprint,
   wait,
+  waitForEvent,
 } from "../utils/helpers";
 import { OptionMarketV2 } from "../packages/psyoptions-ts/src/types";
 import { mintOptionsTx } from "../packages/psyoptions-ts/src";
@@ -160,16 +162,22 @@ describe("closePostExpiration", () => {
         const minterUnderlyingBefore = await underlyingToken.getAccountInfo(
           minterUnderlyingAccount.publicKey
         );
+        let settlementEvent: any;
         try {
-          await closePostExpiration(
+          settlementEvent = await waitForEvent(
             program,
-            minter,
-            size,
-            optionMarket.key,
-            optionMarket.writerTokenMint,
-            minterWriterAcct.publicKey,
-            optionMarket.underlyingAssetPool,
-            minterUnderlyingAccount.publicKey
+            "SettlementEvent",
+            () =>
+              closePostExpiration(
+                program,
+                minter,
+                size,
+                optionMarket.key,
+                optionMarket.writerTokenMint,
+                minterWriterAcct.publicKey,
+                optionMarket.underlyingAssetPool,
+                minterUnderlyingAccount.publicKey
+              )
           );
         } catch (err) {
           console.error((err as Error).toString());
@@ -191,6 +199,20 @@ describe("closePostExpiration", () => {
           minterUnderlyingDiff.toString(),
           size.mul(underlyingAmountPerContract).toString()
         );
+
+        assert.equal(
+          settlementEvent.optionMint.toString(),
+          optionMarket.optionMint.toString()
+        );
+        assert.equal(
+          settlementEvent.fingerprint.toString(),
+          marketFingerprint(optionMarket.optionMint)
+        );
+        assert.equal(
+          settlementEvent.underlyingReclaimed.toString(),
+          minterUnderlyingDiff.toString()
+        );
+        assert.equal(settlementEvent.quoteDistributed.toString(), "0");
       });
     });
     describe("underlying asset pool does not match the OptionMarket", () => {
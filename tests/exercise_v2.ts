@@ -413,6 +413,30 @@ describe("exerciseOption", () => {
         }
       });
     });
+    describe("Quote asset source is the quote asset pool", () => {
+      it("should error", async () => {
+        try {
+          const instruction =
+            psyAmericanInstructions.exerciseOptionsV2Instruction(
+              exerciserProgram,
+              size,
+              optionMarket,
+              exerciserOptionAcct.publicKey,
+              exerciserUnderlyingAcct.publicKey,
+              optionMarket.quoteAssetPool
+            );
+          await exerciserProgram.provider.send(
+            new Transaction().add(instruction)
+          );
+          assert.ok(false);
+        } catch (err) {
+          const programError = parseTransactionError(err);
+          const errMsg =
+            "The same account was passed where distinct accounts are required";
+          assert.equal(programError.msg, errMsg);
+        }
+      });
+    });
   });
 
   describe("Expired option market", () => {
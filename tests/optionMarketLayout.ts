@@ -0,0 +1,121 @@
+import * as anchor from "@project-serum/anchor";
+import assert from "assert";
+
+/**
+ * A serialized OptionMarket account with known field values. If this test
+ * breaks, the on-chain layout changed and existing markets can no longer be
+ * read by clients. Any such change needs a deliberate version bump.
+ *
+ * Generated by Borsh serializing the fields of `KNOWN_MARKET` in declaration
+ * order, prefixed with the Anchor account discriminator
+ * (`sha256("account:OptionMarket")[0..8]`):
+ *   - optionMint, writerTokenMint, underlyingAssetMint, quoteAssetMint:
+ *     32 bytes each, filled with 0x01, 0x02, 0x03 and 0x04
+ *   - underlyingAmountPerContract, quoteAmountPerContract: u64 LE
+ *   - expirationUnixTimestamp: i64 LE
+ *   - underlyingAssetPool, quoteAssetPool, mintFeeAccount,
+ *     exerciseFeeAccount: 32 bytes each, filled with 0x05 through 0x08
+ *   - expired: 1 byte bool
+ *   - bumpSeed: u8
+ */
+const OPTION_MARKET_FIXTURE = Buffer.from(
+  "afeea261357a101d010101010101010101010101010101010101010101010101" +
+    "0101010101010101020202020202020202020202020202020202020202020202" +
+    "0202020202020202030303030303030303030303030303030303030303030303" +
+    "0303030303030303040404040404040404040404040404040404040404040404" +
+    "040404040404040400e1f5050000000000f2052a010000008099cf6100000000" +
+    "0505050505050505050505050505050505050505050505050505050505050505" +
+    "0606060606060606060606060606060606060606060606060606060606060606" +
+    "0707070707070707070707070707070707070707070707070707070707070707" +
+    "0808080808080808080808080808080808080808080808080808080808080808" +
+    "00fe",
+  "hex"
+);
+
+const filledKey = (byte: number) =>
+  new anchor.web3.PublicKey(Buffer.alloc(32, byte));
+
+const KNOWN_MARKET = {
+  optionMint: filledKey(1),
+  writerTokenMint: filledKey(2),
+  underlyingAssetMint: filledKey(3),
+  quoteAssetMint: filledKey(4),
+  underlyingAmountPerContract: new anchor.BN("100000000"),
+  quoteAmountPerContract: new anchor.BN("5000000000"),
+  expirationUnixTimestamp: new anchor.BN("1640995200"),
+  underlyingAssetPool: filledKey(5),
+  quoteAssetPool: filledKey(6),
+  mintFeeAccount: filledKey(7),
+  exerciseFeeAccount: filledKey(8),
+  expired: false,
+  bumpSeed: 254,
+};
+
+describe("OptionMarket layout", () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.PsyAmerican as anchor.Program;
+
+  it("Decodes the fixture into the known field values", () => {
+    const decoded = program.coder.accounts.decode(
+      "OptionMarket",
+      OPTION_MARKET_FIXTURE
+    );
+
+    assert.equal(
+      decoded.optionMint.toString(),
+      KNOWN_MARKET.optionMint.toString()
+    );
+    assert.equal(
+      decoded.writerTokenMint.toString(),
+      KNOWN_MARKET.writerTokenMint.toString()
+    );
+    assert.equal(
+      decoded.underlyingAssetMint.toString(),
+      KNOWN_MARKET.underlyingAssetMint.toString()
+    );
+    assert.equal(
+      decoded.quoteAssetMint.toString(),
+      KNOWN_MARKET.quoteAssetMint.toString()
+    );
+    assert.equal(
+      decoded.underlyingAmountPerContract.toString(),
+      KNOWN_MARKET.underlyingAmountPerContract.toString()
+    );
+    assert.equal(
+      decoded.quoteAmountPerContract.toString(),
+      KNOWN_MARKET.quoteAmountPerContract.toString()
+    );
+    assert.equal(
+      decoded.expirationUnixTimestamp.toString(),
+      KNOWN_MARKET.expirationUnixTimestamp.toString()
+    );
+    assert.equal(
+      decoded.underlyingAssetPool.toString(),
+      KNOWN_MARKET.underlyingAssetPool.toString()
+    );
+    assert.equal(
+      decoded.quoteAssetPool.toString(),
+      KNOWN_MARKET.quoteAssetPool.toString()
+    );
+    assert.equal(
+      decoded.mintFeeAccount.toString(),
+      KNOWN_MARKET.mintFeeAccount.toString()
+    );
+    assert.equal(
+      decoded.exerciseFeeAccount.toString(),
+      KNOWN_MARKET.exerciseFeeAccount.toString()
+    );
+    assert.equal(decoded.expired, KNOWN_MARKET.expired);
+    assert.equal(decoded.bumpSeed, KNOWN_MARKET.bumpSeed);
+  });
+
+  it("Encodes the known field values into the fixture", async () => {
+    const encoded = await program.coder.accounts.encode(
+      "OptionMarket",
+      KNOWN_MARKET
+    );
+
+    assert.ok(encoded.equals(OPTION_MARKET_FIXTURE));
+  });
+});
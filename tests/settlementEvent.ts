@@ -0,0 +1,33 @@
+import * as anchor from "@project-serum/anchor";
+import assert from "assert";
+
+/**
+ * The base64 payload of a `Program data:` log emitted by
+ * `events::emit_settlement`. This is the Anchor event discriminator
+ * (`sha256("event:SettlementEvent")[0..8]`) followed by the Borsh serialized
+ * fields in declaration order:
+ *   - optionMint: 32 bytes filled with 0x01
+ *   - fingerprint: u64 LE, the FNV-1a hash of optionMint
+ *   - underlyingReclaimed, quoteDistributed: u64 LE
+ */
+const SETTLEMENT_EVENT_LOG =
+  "MITabzatPYEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAcWx7/RTmN84AOH1BQAAAAAAAAAAAAAAAA==";
+
+describe("SettlementEvent", () => {
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.PsyAmerican as anchor.Program;
+
+  it("Decodes the emitted log into the settlement totals", () => {
+    const event = program.coder.events.decode(SETTLEMENT_EVENT_LOG);
+
+    assert.equal(event.name, "SettlementEvent");
+    assert.equal(
+      event.data.optionMint.toString(),
+      new anchor.web3.PublicKey(Buffer.alloc(32, 1)).toString()
+    );
+    assert.equal(event.data.fingerprint.toString(), "4098161672289497541");
+    assert.equal(event.data.underlyingReclaimed.toString(), "100000000");
+    assert.equal(event.data.quoteDistributed.toString(), "0");
+  });
+});
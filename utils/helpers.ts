@@ -704,3 +704,47 @@ export const burnWriterForQuote = async (
     signers: [writer],
   });
 };
+
+/**
+ * Run `action` and resolve with the data of the first `eventName` event the
+ * program emits, or reject if none arrives within `timeoutMs`.
+ */
+export const waitForEvent = async <T = any>(
+  program: anchor.Program,
+  eventName: string,
+  action: () => Promise<void>,
+  timeoutMs = 10_000
+): Promise<T> => {
+  let timer: ReturnType<typeof setTimeout> | undefined;
+  let resolveEvent: (event: T) => void = () => {};
+  const event = new Promise<T>((resolve, reject) => {
+    resolveEvent = resolve;
+    timer = setTimeout(
+      () => reject(new Error(`${eventName} was not emitted`)),
+      timeoutMs
+    );
+  });
+  const listener = program.addEventListener(eventName, (data: T) =>
+    resolveEvent(data)
+  );
+  try {
+    await action();
+    return await event;
+  } finally {
+    if (timer) clearTimeout(timer);
+    await program.removeEventListener(listener);
+  }
+};
+
+/**
+ * `OptionMarket::fingerprint`: the 64-bit FNV-1a hash of the option mint.
+ */
+export const marketFingerprint = (optionMint: PublicKey) => {
+  let hash = BigInt("0xcbf29ce484222325");
+  for (const byte of optionMint.toBytes()) {
+    hash =
+      ((hash ^ BigInt(byte)) * BigInt("0x100000001b3")) &
+      BigInt("0xffffffffffffffff");
+  }
+  return hash.toString();
+};